@@ -0,0 +1,181 @@
+use crate::bytecode::{self, Code};
+use crate::vm::Stamp;
+use crate::{Access, Object};
+use std::cell::{Cell, RefCell, RefMut};
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// A PostScript array: a reference to an interval of shared storage, as for
+/// `PsString`.
+#[derive(Clone, Default)]
+pub struct PsArray {
+    data: Rc<Storage>,
+    start: usize,
+    len: usize,
+    access: Access,
+    stamp: Rc<Stamp>,
+}
+
+/// The elements of an array and, once it has been executed as a whole, their
+/// compiled form, dropped whenever they change.
+#[derive(Default)]
+struct Storage {
+    objects: RefCell<Vec<Object>>,
+    code: RefCell<Option<Code>>,
+    /// The number of times the elements were borrowed to change them.
+    version: Cell<u64>,
+}
+
+impl Storage {
+    /// Borrow the elements to change them.
+    fn modify(&self) -> RefMut<'_, Vec<Object>> {
+        self.code.take();
+        self.version.set(self.version.get() + 1);
+        self.objects.borrow_mut()
+    }
+}
+
+impl PsArray {
+    pub fn new(objects: Vec<Object>) -> Self {
+        let len = objects.len();
+        Self {
+            data: Rc::new(Storage {
+                objects: RefCell::new(objects),
+                code: RefCell::default(),
+                version: Cell::default(),
+            }),
+            start: 0,
+            len,
+            access: Access::Unlimited,
+            stamp: Rc::default(),
+        }
+    }
+
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    /// The same array with another access attribute.
+    pub fn with_access(&self, access: Access) -> Self {
+        Self {
+            access,
+            ..self.clone()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Object> {
+        match index < self.len {
+            true => Some(self.data.objects.borrow()[self.start + index].clone()),
+            false => None,
+        }
+    }
+
+    pub fn put(&self, index: usize, object: Object) -> Option<()> {
+        match index < self.len {
+            true => {
+                self.record();
+                self.data.modify()[self.start + index] = object;
+                Some(())
+            }
+            false => None,
+        }
+    }
+
+    /// A copy of the elements.
+    pub fn objects(&self) -> Vec<Object> {
+        self.data.objects.borrow()[self.start..self.start + self.len].to_vec()
+    }
+
+    /// The `count` elements starting at `index`, sharing this array's storage.
+    pub fn interval(&self, index: usize, count: usize) -> Option<PsArray> {
+        match index.checked_add(count) {
+            Some(end) if end <= self.len => Some(Self {
+                data: self.data.clone(),
+                start: self.start + index,
+                len: count,
+                access: self.access,
+                stamp: self.stamp.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Overwrite the elements starting at `index` with `objects`.
+    pub fn put_interval(&self, index: usize, objects: &[Object]) -> Option<()> {
+        match index.checked_add(objects.len()) {
+            Some(end) if end <= self.len => {
+                self.record();
+                let start = self.start + index;
+                self.data.modify()[start..start + objects.len()].clone_from_slice(objects);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// The VM generation the array was created in.
+    pub fn birth(&self) -> u64 {
+        self.stamp.birth()
+    }
+
+    /// Whether the array was allocated in global VM.
+    pub fn is_global(&self) -> bool {
+        self.stamp.is_global()
+    }
+
+    /// Keep the whole storage as it is for `restore`, before modifying it.
+    fn record(&self) {
+        self.stamp.record(|| {
+            let data = self.data.clone();
+            let objects = data.objects.borrow().clone();
+            Box::new(move || *data.modify() = objects)
+        });
+    }
+
+    /// The compiled form of the elements, to execute the array. It is kept
+    /// for the next executions unless the array is an interval of a larger
+    /// one.
+    pub fn code(&self) -> Code {
+        let objects = self.data.objects.borrow();
+        if self.start != 0 || self.len != objects.len() {
+            return bytecode::compile(&objects[self.start..self.start + self.len]);
+        }
+        self.data
+            .code
+            .borrow_mut()
+            .get_or_insert_with(|| bytecode::compile(&objects))
+            .clone()
+    }
+
+    /// Changes whenever the storage of the array is modified, after which
+    /// its `code` must be fetched again.
+    pub fn version(&self) -> u64 {
+        self.data.version.get()
+    }
+
+    /// Whether both reference the same interval of the same storage.
+    pub fn ptr_eq(&self, other: &PsArray) -> bool {
+        Rc::ptr_eq(&self.data, &other.data) && self.start == other.start && self.len == other.len
+    }
+}
+
+impl From<Vec<Object>> for PsArray {
+    fn from(objects: Vec<Object>) -> Self {
+        PsArray::new(objects)
+    }
+}
+
+impl Debug for PsArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Elements are not printed: an array may well contain itself.
+        write!(f, "Array({} elements)", self.len)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::dict;
+use crate::DictStack;
+use crate::Name;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::Operator;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A procedure lowered for execution: one instruction per element, decided
+/// once rather than each time the element is met.
+pub type Code = Rc<[Instruction]>;
+
+pub enum Instruction {
+    /// Push a literal object. Procedures met in a procedure are pushed too.
+    Push(Object),
+    /// Run an operator, as put in procedures by `bind`.
+    Operator(Operator),
+    /// Look up an executable name and execute its value.
+    Name(NameRef),
+    /// Execute an executable string or file.
+    Exec(Object),
+}
+
+/// An executable name in a procedure, with the value it was last found to
+/// have. The value is reused as long as no dictionary nor the dictionary
+/// stack changed since, so that redefinitions are seen as in PostScript.
+pub struct NameRef {
+    name: Name,
+    cache: RefCell<Option<(u64, Object)>>,
+}
+
+impl NameRef {
+    pub fn name(&self) -> Name {
+        self.name
+    }
+
+    /// The value of the name in `dict_stack`.
+    pub fn lookup(&self, dict_stack: &DictStack) -> Option<Object> {
+        let epoch = dict::epoch();
+        if let Some((seen, value)) = &*self.cache.borrow() {
+            if *seen == epoch {
+                return Some(value.clone());
+            }
+        }
+
+        let value = dict_stack.get(&self.name.into())?;
+        *self.cache.borrow_mut() = Some((epoch, value.clone()));
+        Some(value)
+    }
+}
+
+impl From<&Object> for Instruction {
+    fn from(object: &Object) -> Self {
+        match object {
+            Object::Operator(Executable, op) => Instruction::Operator(*op),
+            Object::Name(Executable, name) => Instruction::Name(NameRef {
+                name: *name,
+                cache: RefCell::new(None),
+            }),
+            Object::String(Executable, _) | Object::File(Executable, _) => {
+                Instruction::Exec(object.clone())
+            }
+            other => Instruction::Push(other.clone()),
+        }
+    }
+}
+
+/// Lower the elements of a procedure.
+pub fn compile(objects: &[Object]) -> Code {
+    objects.iter().map(Instruction::from).collect()
+}
@@ -0,0 +1,193 @@
+use crate::vm::Stamp;
+use crate::ObjectMode::*;
+use crate::{Access, Name, Object};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+thread_local! {
+    static EPOCH: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A counter bumped whenever a dictionary or the dictionary stack changes:
+/// name lookups made in the same epoch give the same results.
+pub fn epoch() -> u64 {
+    EPOCH.get()
+}
+
+pub fn bump_epoch() {
+    EPOCH.set(EPOCH.get() + 1);
+}
+
+/// A dictionary key: any object but null. As for `eq`, a string stands for
+/// the name with the same text, a real with an integral value for the
+/// integer, and composite objects are compared by identity.
+#[derive(Clone, Debug)]
+pub struct Key(Object);
+
+impl Key {
+    /// The key designated by `object`, `None` for null.
+    pub fn new(object: &Object) -> Option<Key> {
+        let key = match object {
+            Object::Null => return None,
+            Object::String(_, s) => Object::Name(Literal, Name::new(&s.bytes())),
+            Object::Real(r) if r.fract() == 0.0 && r.abs() < i64::MAX as f64 => {
+                Object::Integer(*r as i64)
+            }
+            other => other.clone(),
+        };
+        Some(Key(key))
+    }
+
+    /// The key as an object, as pushed by `forall`.
+    pub fn object(&self) -> &Object {
+        &self.0
+    }
+
+    pub fn into_object(self) -> Object {
+        self.0
+    }
+}
+
+impl From<Name> for Key {
+    fn from(name: Name) -> Self {
+        Key(Object::Name(Literal, name))
+    }
+}
+
+impl From<&str> for Key {
+    fn from(text: &str) -> Self {
+        Key::from(Name::from(text))
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ps_eq(&other.0)
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // composite keys are rare: they all hash alike
+        match &self.0 {
+            Object::Name(_, name) => name.hash(state),
+            Object::Integer(i) => i.hash(state),
+            Object::Real(r) => r.to_bits().hash(state),
+            Object::Bool(b) => b.hash(state),
+            Object::Operator(_, op) => op.hash(state),
+            Object::Save(id) => id.hash(state),
+            _ => (),
+        }
+    }
+}
+
+/// A PostScript dictionary. Cloning a `Dict` yields another reference to the
+/// same storage, so a definition made through one is seen through all. Unlike
+/// arrays and strings, the access attribute is shared as well.
+#[derive(Clone, Default)]
+pub struct Dict {
+    map: Rc<RefCell<HashMap<Key, Object>>>,
+    access: Rc<Cell<Access>>,
+    stamp: Rc<Stamp>,
+}
+
+impl Dict {
+    pub fn new() -> Self {
+        Dict::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: Rc::new(RefCell::new(HashMap::with_capacity(capacity))),
+            access: Rc::default(),
+            stamp: Rc::default(),
+        }
+    }
+
+    pub fn access(&self) -> Access {
+        self.access.get()
+    }
+
+    pub fn set_access(&self, access: Access) {
+        self.record();
+        self.access.set(access);
+    }
+
+    pub fn get(&self, key: &Key) -> Option<Object> {
+        self.map.borrow().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: Key, object: Object) {
+        self.record();
+        bump_epoch();
+        self.map.borrow_mut().insert(key, object);
+    }
+
+    pub fn remove(&self, key: &Key) -> Option<Object> {
+        self.record();
+        bump_epoch();
+        self.map.borrow_mut().remove(key)
+    }
+
+    pub fn contains_key(&self, key: &Key) -> bool {
+        self.map.borrow().contains_key(key)
+    }
+
+    /// A snapshot of the entries, in no particular order.
+    pub fn entries(&self) -> Vec<(Key, Object)> {
+        self.map
+            .borrow()
+            .iter()
+            .map(|(key, object)| (key.clone(), object.clone()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.borrow().is_empty()
+    }
+
+    /// The VM generation the dictionary was created in.
+    pub fn birth(&self) -> u64 {
+        self.stamp.birth()
+    }
+
+    /// Whether the dictionary was allocated in global VM.
+    pub fn is_global(&self) -> bool {
+        self.stamp.is_global()
+    }
+
+    /// Keep the entries and the access as they are for `restore`, before
+    /// modifying them.
+    fn record(&self) {
+        self.stamp.record(|| {
+            let (map, access) = (self.map.clone(), self.access.clone());
+            let (entries, value) = (map.borrow().clone(), access.get());
+            Box::new(move || {
+                *map.borrow_mut() = entries;
+                access.set(value);
+                bump_epoch();
+            })
+        });
+    }
+
+    /// Whether both are references to the same dictionary.
+    pub fn ptr_eq(&self, other: &Dict) -> bool {
+        Rc::ptr_eq(&self.map, &other.map)
+    }
+}
+
+impl Debug for Dict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // not the entries, as for arrays
+        write!(f, "Dict({} entries)", self.len())
+    }
+}
@@ -1,77 +1,299 @@
+use crate::dict;
+use crate::vm::Vm;
+use crate::ErrorKind::{self, *};
 use crate::ObjectMode::*;
-use crate::{Object, Operator::*};
-use std::collections::HashMap;
+use crate::{Access, Dict, Key, Object, Operator, Operator::*};
 
 use log::debug;
 
 pub struct DictStack {
-    stack: Vec<HashMap<String, Object>>,
+    stack: Vec<Dict>,
+    errordict: Dict,
+    dollar_error: Dict,
 }
 
-const SYSTEMDICT: [(&str, Object); 26] = [
-    ("]", Object::Operator(Executable, EndArray)),
+const SYSTEMDICT: [(&str, Object); 114] = [
     ("=", Object::Operator(Executable, PopAndPrint)),
+    ("==", Object::Operator(Executable, PopAndPrintSyntax)),
+    (
+        "==only",
+        Object::Operator(Executable, PopAndPrintSyntaxOnly),
+    ),
+    ("=only", Object::Operator(Executable, PopAndPrintOnly)),
+    (">>", Object::Operator(Executable, EndDict)),
+    ("]", Object::Operator(Executable, EndArray)),
+    ("abs", Object::Operator(Executable, Abs)),
     ("add", Object::Operator(Executable, Add)),
+    ("aload", Object::Operator(Executable, ALoad)),
+    ("anchorsearch", Object::Operator(Executable, AnchorSearch)),
+    ("and", Object::Operator(Executable, And)),
+    ("array", Object::Operator(Executable, Array)),
+    ("astore", Object::Operator(Executable, AStore)),
+    ("atan", Object::Operator(Executable, Atan)),
+    ("begin", Object::Operator(Executable, Begin)),
+    ("bind", Object::Operator(Executable, Bind)),
+    ("bitshift", Object::Operator(Executable, BitShift)),
+    (
+        "bytesavailable",
+        Object::Operator(Executable, BytesAvailable),
+    ),
+    ("ceiling", Object::Operator(Executable, Ceiling)),
     ("clear", Object::Operator(Executable, Clear)),
     ("cleartomark", Object::Operator(Executable, ClearToMark)),
+    ("closefile", Object::Operator(Executable, CloseFile)),
     ("copy", Object::Operator(Executable, Copy)),
+    ("cos", Object::Operator(Executable, Cos)),
+    (
+        "countdictstack",
+        Object::Operator(Executable, CountDictStack),
+    ),
     ("counttomark", Object::Operator(Executable, CountToMark)),
+    ("currentdict", Object::Operator(Executable, CurrentDict)),
+    ("currentfile", Object::Operator(Executable, CurrentFile)),
+    ("currentglobal", Object::Operator(Executable, CurrentGlobal)),
+    ("cvi", Object::Operator(Executable, Cvi)),
+    ("cvlit", Object::Operator(Executable, Cvlit)),
+    ("cvn", Object::Operator(Executable, Cvn)),
+    ("cvr", Object::Operator(Executable, Cvr)),
+    ("cvrs", Object::Operator(Executable, Cvrs)),
+    ("cvs", Object::Operator(Executable, Cvs)),
+    ("cvx", Object::Operator(Executable, Cvx)),
     ("def", Object::Operator(Executable, Def)),
+    ("dict", Object::Operator(Executable, Operator::Dict)),
+    (
+        "dictstack",
+        Object::Operator(Executable, Operator::DictStack),
+    ),
     ("div", Object::Operator(Executable, Div)),
     ("dup", Object::Operator(Executable, Dup)),
+    ("end", Object::Operator(Executable, End)),
     ("eq", Object::Operator(Executable, Eq)),
     ("exch", Object::Operator(Executable, Exch)),
     ("exec", Object::Operator(Executable, Exec)),
+    ("executeonly", Object::Operator(Executable, ExecuteOnly)),
+    ("exit", Object::Operator(Executable, Exit)),
+    ("exp", Object::Operator(Executable, Exp)),
+    ("file", Object::Operator(Executable, File)),
+    ("floor", Object::Operator(Executable, Floor)),
+    ("flush", Object::Operator(Executable, Flush)),
+    ("flushfile", Object::Operator(Executable, FlushFile)),
+    ("for", Object::Operator(Executable, For)),
+    ("forall", Object::Operator(Executable, Forall)),
+    ("ge", Object::Operator(Executable, Ge)),
+    ("get", Object::Operator(Executable, Get)),
+    ("getinterval", Object::Operator(Executable, GetInterval)),
     ("gt", Object::Operator(Executable, Gt)),
-    ("ifelse", Object::Operator(Executable, IfElse)),
+    ("handleerror", Object::Operator(Executable, HandleError)),
+    ("idiv", Object::Operator(Executable, IDiv)),
     ("if", Object::Operator(Executable, If)),
+    ("ifelse", Object::Operator(Executable, IfElse)),
     ("index", Object::Operator(Executable, Index)),
+    ("known", Object::Operator(Executable, Known)),
+    ("le", Object::Operator(Executable, Le)),
+    ("length", Object::Operator(Executable, Length)),
+    ("ln", Object::Operator(Executable, Ln)),
     ("load", Object::Operator(Executable, Load)),
+    ("log", Object::Operator(Executable, Log)),
+    ("loop", Object::Operator(Executable, Loop)),
+    ("lt", Object::Operator(Executable, Lt)),
     ("mod", Object::Operator(Executable, Mod)),
     ("mul", Object::Operator(Executable, Mul)),
     ("ne", Object::Operator(Executable, Ne)),
+    ("neg", Object::Operator(Executable, Neg)),
+    ("noaccess", Object::Operator(Executable, NoAccess)),
+    ("not", Object::Operator(Executable, Not)),
+    ("null", Object::Null),
+    ("or", Object::Operator(Executable, Or)),
     ("pop", Object::Operator(Executable, Pop)),
+    ("print", Object::Operator(Executable, Print)),
     ("pstack", Object::Operator(Executable, Pstack)),
+    ("put", Object::Operator(Executable, Put)),
+    ("putinterval", Object::Operator(Executable, PutInterval)),
+    ("rcheck", Object::Operator(Executable, RCheck)),
+    ("read", Object::Operator(Executable, Read)),
+    ("readline", Object::Operator(Executable, ReadLine)),
+    ("readonly", Object::Operator(Executable, ReadOnly)),
+    ("readstring", Object::Operator(Executable, ReadString)),
     ("repeat", Object::Operator(Executable, Repeat)),
+    ("restore", Object::Operator(Executable, Restore)),
     ("roll", Object::Operator(Executable, Roll)),
+    ("round", Object::Operator(Executable, Round)),
+    ("run", Object::Operator(Executable, Run)),
+    ("save", Object::Operator(Executable, Save)),
+    ("search", Object::Operator(Executable, Search)),
+    ("setglobal", Object::Operator(Executable, SetGlobal)),
+    ("sin", Object::Operator(Executable, Sin)),
+    ("sqrt", Object::Operator(Executable, Sqrt)),
+    ("stack", Object::Operator(Executable, Stack)),
+    ("stop", Object::Operator(Executable, Stop)),
+    ("stopped", Object::Operator(Executable, Stopped)),
+    ("string", Object::Operator(Executable, String)),
     ("sub", Object::Operator(Executable, Sub)),
+    ("token", Object::Operator(Executable, Token)),
+    ("truncate", Object::Operator(Executable, Truncate)),
+    ("type", Object::Operator(Executable, Type)),
+    ("undef", Object::Operator(Executable, Undef)),
+    ("wcheck", Object::Operator(Executable, WCheck)),
+    ("where", Object::Operator(Executable, Where)),
+    ("write", Object::Operator(Executable, Operator::Write)),
+    ("writestring", Object::Operator(Executable, WriteString)),
+    ("xcheck", Object::Operator(Executable, XCheck)),
+    ("xor", Object::Operator(Executable, Xor)),
 ];
 
-impl Default for DictStack {
-    fn default() -> Self {
-        let mut ds = DictStack { stack: Vec::new() };
-        ds.stack.push(DictStack::build_systemdict());
-        ds.stack.push(HashMap::new()); // userdict
-        ds
-    }
-}
+const ERRORS: [ErrorKind; 22] = [
+    DictFull,
+    DictStackOverflow,
+    DictStackUnderflow,
+    ExecStackOverflow,
+    Interrupt,
+    InvalidAccess,
+    InvalidExit,
+    InvalidFileAccess,
+    InvalidRestore,
+    IoError,
+    LimitCheck,
+    RangeCheck,
+    StackOverflow,
+    StackUnderflow,
+    SyntaxError,
+    TypeCheck,
+    Undefined,
+    UndefinedFileName,
+    UndefinedResult,
+    UnmatchedMark,
+    Unregistered,
+    VmError,
+];
 
 impl DictStack {
-    pub fn new() -> Self {
-        DictStack::default()
+    /// The initial dictionary stack, allocated in `vm`.
+    pub fn new(vm: &Vm) -> Self {
+        let _vm = vm.enter();
+        // errordict, $error and userdict are local, the others global
+        let errordict = DictStack::build_errordict();
+        let dollar_error = DictStack::build_dollar_error();
+        let userdict = Dict::new();
+        let global = vm.is_global();
+        vm.set_global(true);
+        let systemdict = DictStack::build_systemdict();
+        let globaldict = Dict::new();
+        vm.set_global(global);
+
+        systemdict.insert("errordict".into(), Object::Dict(Literal, errordict.clone()));
+        systemdict.insert("$error".into(), Object::Dict(Literal, dollar_error.clone()));
+        systemdict.insert("userdict".into(), Object::Dict(Literal, userdict.clone()));
+        systemdict.insert(
+            "globaldict".into(),
+            Object::Dict(Literal, globaldict.clone()),
+        );
+        systemdict.insert(
+            "systemdict".into(),
+            Object::Dict(Literal, systemdict.clone()),
+        );
+        systemdict.set_access(Access::ReadOnly);
+
+        DictStack {
+            stack: vec![systemdict, globaldict, userdict],
+            errordict,
+            dollar_error,
+        }
     }
 
-    pub fn def(&mut self, key: String, val: Object) {
-        debug!("register {key}:{val}");
-        let mut top = self.stack.pop().unwrap();
-        top.insert(key, val);
-        self.stack.push(top);
+    pub fn def(&mut self, key: Key, val: Object) {
+        debug!("register {}:{val}", key.object());
+        self.stack.last().unwrap().insert(key, val);
     }
 
-    pub fn get(&self, key: &str) -> Option<Object> {
+    pub fn get(&self, key: &Key) -> Option<Object> {
         for dict in self.stack.iter().rev() {
             if let Some(object) = dict.get(key) {
-                return Some(object.clone());
+                return Some(object);
             }
         }
         None
     }
 
-    fn build_systemdict() -> HashMap<String, Object> {
-        let mut dict = HashMap::new();
+    /// The topmost dictionary defining `key`.
+    pub fn find(&self, key: &Key) -> Option<Dict> {
+        self.stack
+            .iter()
+            .rev()
+            .find(|dict| dict.contains_key(key))
+            .cloned()
+    }
+
+    pub fn current(&self) -> Dict {
+        self.stack.last().unwrap().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// The dictionaries, bottom first.
+    pub fn dicts(&self) -> Vec<Dict> {
+        self.stack.clone()
+    }
+
+    pub fn begin(&mut self, dict: Dict) {
+        dict::bump_epoch();
+        self.stack.push(dict);
+    }
+
+    /// Pop the current dictionary; systemdict, globaldict and userdict cannot
+    /// be popped.
+    pub fn end(&mut self) -> Option<Dict> {
+        if self.stack.len() > 3 {
+            dict::bump_epoch();
+            self.stack.pop()
+        } else {
+            None
+        }
+    }
+
+    pub fn errordict(&self) -> &Dict {
+        &self.errordict
+    }
+
+    pub fn dollar_error(&self) -> &Dict {
+        &self.dollar_error
+    }
+
+    fn build_systemdict() -> Dict {
+        let dict = Dict::new();
         for (name, op) in SYSTEMDICT {
-            dict.insert(name.to_string(), op);
+            dict.insert(name.into(), op);
         }
         dict
     }
+
+    fn build_errordict() -> Dict {
+        let dict = Dict::new();
+        for kind in ERRORS {
+            dict.insert(
+                kind.name().into(),
+                Object::Operator(Executable, ErrorHandler(kind)),
+            );
+        }
+        dict.insert(
+            "handleerror".into(),
+            Object::Operator(Executable, HandleError),
+        );
+        dict
+    }
+
+    fn build_dollar_error() -> Dict {
+        let dict = Dict::new();
+        dict.insert("newerror".into(), Object::Bool(false));
+        dict.insert("errorname".into(), Object::Null);
+        dict.insert("command".into(), Object::Null);
+        dict.insert("ostack".into(), Object::Null);
+        dict.insert("recordstacks".into(), Object::Bool(true));
+        dict
+    }
 }
@@ -1,28 +1,54 @@
-use crate::xstack::RepeatRunner;
+use crate::bytecode::Instruction;
+use crate::error::describe_command;
+use crate::error::ErrorKind::{self, *};
+use crate::vm::Vm;
+use crate::xstack::{
+    FileRunner, ForControl, ForRunner, ForallRunner, LoopRunner, RepeatRunner, Step, StoppedRunner,
+};
 use crate::DictStack;
+use crate::Error;
 use crate::ExecStack;
+use crate::Name;
 use crate::Object;
 use crate::ObjectMode::*;
-use crate::OnceRunner;
 use crate::Operator;
+use crate::Position;
+use crate::PsArray;
+use crate::PsFile;
 
 use std::cmp::Ordering;
+use std::io::{self, BufReader, Write};
 
 use log::debug;
 
+mod attributes;
+mod composite;
+mod conversions;
+mod dicts;
+mod files;
+mod math;
+mod memory;
+mod output;
+
 pub struct Engine {
     exec_stack: ExecStack,
     dict_stack: DictStack,
     main_stack: Vec<Object>,
+    /// The VM objects are allocated in while the engine runs.
+    vm: Vm,
+    pending_error: Option<Error>,
+    uncaught_error: Option<Error>,
+    reporting_error: bool,
+    /// Where the last error was met, if in a file being executed.
+    position: Option<Position>,
+    stdin: PsFile,
+    stdout: PsFile,
+    stderr: PsFile,
 }
 
 impl Default for Engine {
     fn default() -> Self {
-        Self {
-            exec_stack: ExecStack::new(),
-            dict_stack: DictStack::new(),
-            main_stack: Vec::new(),
-        }
+        Engine::with_output(Box::new(io::stdout()), Box::new(io::stderr()))
     }
 }
 
@@ -31,6 +57,24 @@ impl Engine {
         Engine::default()
     }
 
+    /// An engine writing its standard output and error to the given writers.
+    pub fn with_output(stdout: Box<dyn Write>, stderr: Box<dyn Write>) -> Self {
+        let vm = Vm::new();
+        Self {
+            exec_stack: ExecStack::new(),
+            dict_stack: DictStack::new(&vm),
+            main_stack: Vec::new(),
+            vm,
+            pending_error: None,
+            uncaught_error: None,
+            reporting_error: false,
+            position: None,
+            stdin: PsFile::reader("%stdin", Box::new(BufReader::new(io::stdin()))),
+            stdout: PsFile::writer("%stdout", stdout),
+            stderr: PsFile::writer("%stderr", stderr),
+        }
+    }
+
     pub fn get_stack_size(&self) -> usize {
         self.main_stack.len()
     }
@@ -40,29 +84,175 @@ impl Engine {
         self.main_stack.push(object)
     }
 
+    /// The `%stdin` file.
+    pub fn stdin(&self) -> PsFile {
+        self.stdin.clone()
+    }
+
     pub fn get_object_by_name(&self, name: &str) -> Option<Object> {
-        self.dict_stack.get(name)
+        self.dict_stack.get(&name.into())
+    }
+
+    pub fn process_execution_stack(&mut self) -> Result<(), Error> {
+        let _vm = self.vm.enter();
+        while let Some(step) = self
+            .exec_stack
+            .next_step(&mut self.main_stack, &self.dict_stack)
+        {
+            let result = match step {
+                Ok(Step::Instruction(code, index)) => self.execute_instruction(&code[index]),
+                Ok(Step::Object(object)) => self.process_object(object),
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                self.handle_error(error)?;
+            }
+        }
+        self.pending_error = None;
+
+        match self.uncaught_error.take() {
+            Some(error) => {
+                self.report_error(&error);
+                Err(error)
+            }
+            None => Ok(()),
+        }
     }
 
-    pub fn process_execution_stack(&mut self) -> Result<(), String> {
-        while let Some(object) = self.exec_stack.get_object() {
-            self.process_object(object)?;
+    /// Process `object` and everything it schedules on the execution stack,
+    /// going through errordict if anything fails.
+    pub fn execute_object(&mut self, object: Object) -> Result<(), Error> {
+        let _vm = self.vm.enter();
+        if let Err(error) = self.process_object(object) {
+            self.handle_error(error)?;
+        }
+        self.process_execution_stack()
+    }
+
+    /// Execute the PostScript program read from `file`.
+    pub fn execute_file(&mut self, file: PsFile) -> Result<(), Error> {
+        self.exec_stack.push(Box::new(FileRunner::new(file)));
+        self.process_execution_stack()
+    }
+
+    /// Signal an error detected outside of the engine (e.g. by the scanner).
+    pub fn raise(&mut self, error: Error) -> Result<(), Error> {
+        self.handle_error(error)?;
+        self.process_execution_stack()
+    }
+
+    /// Restore the operands of the failed command, push the command itself
+    /// and schedule the matching errordict procedure. Without handler the
+    /// execution is aborted, the error is reported on the standard error and
+    /// returned as is.
+    fn handle_error(&mut self, error: Error) -> Result<(), Error> {
+        debug!("error {error}");
+        self.position = self.exec_stack.position();
+        self.main_stack.extend(error.operands.iter().cloned());
+
+        let handler = match self.reporting_error {
+            true => None,
+            false => self.dict_stack.errordict().get(&error.kind.name().into()),
+        };
+
+        match handler {
+            Some(handler) => {
+                self.main_stack
+                    .push(error.command.clone().unwrap_or(Object::Null));
+                self.pending_error = Some(error);
+                self.push_exec(handler);
+                Ok(())
+            }
+            None => {
+                self.exec_stack.clear();
+                if !self.reporting_error {
+                    self.report_unhandled(&error);
+                }
+                Err(error)
+            }
         }
-        Ok(())
     }
 
-    pub fn run_operator(&mut self, op: Operator) -> Result<(), String> {
+    /// Run errordict's handleerror. Errors raised meanwhile are not handled.
+    /// The error is reported on the standard error instead if handleerror
+    /// did not take it from $error, as shown by `newerror` being reset.
+    fn report_error(&mut self, error: &Error) {
+        let recorded = self.has_new_error();
+        if let Some(handler) = self.dict_stack.errordict().get(&"handleerror".into()) {
+            self.reporting_error = true;
+            self.push_exec(handler);
+            if let Err(error) = self.process_execution_stack() {
+                debug!("error in handleerror: {error}");
+            }
+            self.reporting_error = false;
+        }
+        if !recorded || self.has_new_error() {
+            self.report_unhandled(error);
+        }
+    }
+
+    fn has_new_error(&self) -> bool {
+        let newerror = self.dict_stack.dollar_error().get(&"newerror".into());
+        matches!(newerror, Some(Object::Bool(true)))
+    }
+
+    /// Report an error on the standard error, where handleerror did not.
+    fn report_unhandled(&mut self, error: &Error) {
+        let command = error.command.clone().unwrap_or(Object::Null);
+        let report = self.format_report(error.kind.name(), &command, Some(&self.main_stack));
+        if let Err(error) = self.stderr.write(report.as_bytes()) {
+            debug!("error on reporting: {error}");
+        }
+    }
+
+    /// Schedule the execution of `object` as `exec` would.
+    fn push_exec(&mut self, object: Object) {
+        match object {
+            Object::Array(Executable, proc) => self.exec_stack.call(&proc),
+            Object::String(Executable, string) => {
+                self.exec_stack.push(Box::new(FileRunner::string(&string)))
+            }
+            Object::File(Executable, file) => self.exec_stack.push(Box::new(FileRunner::new(file))),
+            other => self.exec_stack.call(&PsArray::new(vec![other])),
+        }
+    }
+
+    pub fn run_operator(&mut self, op: Operator) -> Result<(), Error> {
         use Operator::*;
 
-        match op {
+        let result = match op {
             Add => self.add(),
             Clear => self.clear(),
             Copy => self.copy(),
             Index => self.index(),
             Def => self.def(),
             Div => self.div(),
+            IDiv => self.idiv(),
             Exec => self.exec(),
             PopAndPrint => self.pop_and_print(),
+            PopAndPrintOnly => self.pop_and_print_only(),
+            PopAndPrintSyntax => self.pop_and_print_syntax(),
+            PopAndPrintSyntaxOnly => self.pop_and_print_syntax_only(),
+            Print => self.print(),
+            Flush => self.flush(),
+            File => self.file(),
+            FlushFile => self.flushfile(),
+            Read => self.read(),
+            Operator::Write => self.write(),
+            ReadLine => self.readline(),
+            ReadString => self.readstring(),
+            WriteString => self.writestring(),
+            CloseFile => self.closefile(),
+            BytesAvailable => self.bytesavailable(),
+            CurrentFile => self.currentfile(),
+            Run => self.run(),
+            Token => self.token(),
+            Save => self.save(),
+            Restore => self.restore(),
+            SetGlobal => self.setglobal(),
+            CurrentGlobal => self.currentglobal(),
+            Bind => self.bind(),
+            Stack => self.stack(),
             Dup => self.dup(),
             Eq => self.eq(),
             Ne => self.ne(),
@@ -81,32 +271,85 @@ impl Engine {
             Repeat => self.repeat(),
             If => self.cond_if(),
             IfElse => self.cond_ifelse(),
-        }
+            Neg => self.neg(),
+            Abs => self.abs(),
+            Lt => self.lt(),
+            Le => self.le(),
+            Ge => self.ge(),
+            Sqrt => self.sqrt(),
+            Exp => self.exp(),
+            Ln => self.ln(),
+            Log => self.log(),
+            Sin => self.sin(),
+            Cos => self.cos(),
+            Atan => self.atan(),
+            Ceiling => self.ceiling(),
+            Floor => self.floor(),
+            Round => self.round(),
+            Truncate => self.truncate(),
+            Cvi => self.cvi(),
+            Cvr => self.cvr(),
+            Cvn => self.cvn(),
+            Cvs => self.cvs(),
+            Cvrs => self.cvrs(),
+            Length => self.length(),
+            GetInterval => self.getinterval(),
+            Array => self.array(),
+            ALoad => self.aload(),
+            AStore => self.astore(),
+            PutInterval => self.putinterval(),
+            String => self.string(),
+            Search => self.search(),
+            AnchorSearch => self.anchorsearch(),
+            Type => self.type_of(),
+            Cvx => self.cvx(),
+            Cvlit => self.cvlit(),
+            XCheck => self.xcheck(),
+            RCheck => self.rcheck(),
+            WCheck => self.wcheck(),
+            ReadOnly => self.readonly(),
+            ExecuteOnly => self.executeonly(),
+            NoAccess => self.noaccess(),
+            And => self.and(),
+            Or => self.or(),
+            Xor => self.xor(),
+            Not => self.not(),
+            BitShift => self.bitshift(),
+            Begin => self.begin(),
+            Operator::Dict => self.dict(),
+            EndDict => self.enddict(),
+            Known => self.known(),
+            Where => self.where_defined(),
+            Undef => self.undef(),
+            CurrentDict => self.currentdict(),
+            CountDictStack => self.countdictstack(),
+            Operator::DictStack => self.dictstack(),
+            End => self.end(),
+            Get => self.get(),
+            Put => self.put(),
+            HandleError => self.handleerror(),
+            Stop => self.stop(),
+            Stopped => self.stopped(),
+            Exit => self.exit(),
+            For => self.for_loop(),
+            Loop => self.loop_forever(),
+            Forall => self.forall(),
+            ErrorHandler(kind) => self.error_handler(kind),
+        };
+        result.map_err(|e| e.in_command(Object::Operator(Executable, op)))
     }
 
-    pub fn process_object(&mut self, object: Object) -> Result<(), String> {
+    pub fn process_object(&mut self, object: Object) -> Result<(), Error> {
         debug!("process_object: {object}");
         use Object::*;
 
         match object {
-            Name(Executable, name) => {
-                if let Some(obj) = self.dict_stack.get(&name) {
-                    match obj {
-                        Array(Executable, proc) => {
-                            self.exec_stack.push(Box::new(OnceRunner::new(proc)));
-                        }
-                        Operator(Executable, op) => {
-                            self.run_operator(op)?;
-                        }
-                        other => {
-                            self.exec_stack.push(Box::new(OnceRunner::new(vec![other])));
-                        }
-                    }
-                } else {
-                    return Err(format!("ExeName '{name}' not found"));
-                }
-            }
+            Name(Executable, name) => match self.dict_stack.get(&name.into()) {
+                Some(value) => self.execute_value(name, value)?,
+                None => return Err(Error::new(Undefined).in_command(Name(Executable, name))),
+            },
             Operator(Executable, op) => self.run_operator(op)?,
+            object @ (String(Executable, _) | File(Executable, _)) => self.push_exec(object),
             other => {
                 self.push(other.clone());
             }
@@ -114,72 +357,124 @@ impl Engine {
         Ok(())
     }
 
-    pub fn add(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Integer(i1 + i2));
+    /// Execute an instruction of a compiled procedure: what `process_object`
+    /// does for the element it was compiled from.
+    fn execute_instruction(&mut self, instruction: &Instruction) -> Result<(), Error> {
+        match instruction {
+            Instruction::Push(object) => {
+                self.main_stack.push(object.clone());
+                Ok(())
+            }
+            Instruction::Operator(op) => self.run_operator(*op),
+            Instruction::Name(name) => match name.lookup(&self.dict_stack) {
+                Some(value) => self.execute_value(name.name(), value),
+                None => {
+                    Err(Error::new(Undefined).in_command(Object::Name(Executable, name.name())))
+                }
+            },
+            Instruction::Exec(object) => {
+                self.push_exec(object.clone());
                 Ok(())
             }
-            (None, _) | (_, None) => Err("'add' stack underflow".to_string()),
-            (Some(a), Some(b)) => Err(format!("'add' not implemented: {:?}, {:?}", a, b)),
         }
     }
 
-    pub fn exch(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(i1), Some(i2)) => {
-                self.main_stack.push(i1);
-                self.main_stack.push(i2);
+    /// Execute `value`, found for the executable name `name`. Procedures are
+    /// called, names scheduled so that aliases looping on each other do not
+    /// exhaust the native stack.
+    fn execute_value(&mut self, name: Name, value: Object) -> Result<(), Error> {
+        match value {
+            Object::Operator(Executable, op) => self.run_operator(op),
+            value @ (Object::Array(Executable, _)
+            | Object::String(Executable, _)
+            | Object::File(Executable, _))
+                if !value.is_executable() =>
+            {
+                Err(Error::new(InvalidAccess).in_command(Object::Name(Executable, name)))
+            }
+            value @ (Object::Array(Executable, _) | Object::Name(Executable, _)) => {
+                self.push_exec(value);
                 Ok(())
             }
-            (None, _) | (_, None) => Err("'exch' stack underflow".to_string()),
+            value => self.process_object(value),
         }
     }
 
-    pub fn endarray(&mut self) -> Result<(), String> {
+    /// Pop the `N` topmost operands, returned bottom first. The stack is left
+    /// untouched when it does not hold enough operands.
+    fn pop_operands<const N: usize>(&mut self) -> Result<[Object; N], Error> {
+        if self.main_stack.len() < N {
+            return Err(Error::new(StackUnderflow));
+        }
+        let operands: Vec<Object> = self.main_stack.split_off(self.main_stack.len() - N);
+        Ok(operands.try_into().unwrap())
+    }
+
+    pub fn exch(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        self.main_stack.push(b);
+        self.main_stack.push(a);
+        Ok(())
+    }
+
+    pub fn endarray(&mut self) -> Result<(), Error> {
         let array = self.build_array()?;
         self.main_stack.push(array);
         Ok(())
     }
 
-    pub fn roll(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(j)), Some(Object::Integer(n))) => {
+    pub fn roll(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(n), Object::Integer(j)] => {
                 let (len, unsigned_n) = (self.main_stack.len(), n as usize);
-                if n == 0 || n == 1 {
-                    Ok(())
-                } else if n < 0 {
-                    Err("'roll' negative roll range".to_string())
+                if n < 0 {
+                    Err(Error::with_operands(
+                        RangeCheck,
+                        vec![Object::Integer(n), Object::Integer(j)],
+                    ))
                 } else if len < unsigned_n {
-                    Err("'roll' stack too short".to_string())
+                    Err(Error::with_operands(
+                        StackUnderflow,
+                        vec![Object::Integer(n), Object::Integer(j)],
+                    ))
+                } else if n == 0 || n == 1 {
+                    Ok(())
                 } else {
                     let index = len - unsigned_n;
                     let mut tops: Vec<Object> = self.main_stack.drain(index..).collect();
+                    let shift = (j.unsigned_abs() % n as u64) as usize;
                     match j.cmp(&0) {
-                        Ordering::Less => {
-                            let j = -j;
-                            tops.rotate_left(j as usize);
-                        }
-                        Ordering::Greater => tops.rotate_right(j as usize),
-                        Ordering::Equal => return Ok(()),
+                        Ordering::Less => tops.rotate_left(shift),
+                        Ordering::Greater => tops.rotate_right(shift),
+                        Ordering::Equal => (),
                     }
                     self.main_stack.extend(tops);
                     Ok(())
                 }
             }
-            (Some(_), Some(_)) => Err("'roll', type mismatch".to_string()),
-            (None, _) | (_, None) => Err("'roll' stack underflow".to_string()),
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
         }
     }
 
-    pub fn copy(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(Object::Integer(n)) => {
+    /// Either duplicate the n topmost operands, or copy a composite object
+    /// into another one.
+    pub fn copy(&mut self) -> Result<(), Error> {
+        if let Some(Object::Array(..) | Object::String(..) | Object::Dict(..)) =
+            self.main_stack.last()
+        {
+            return self.copy_composite();
+        }
+
+        match self.pop_operands()? {
+            [Object::Integer(n)] => {
                 let (len, unsigned_n) = (self.main_stack.len(), n as usize);
                 if n < 0 {
-                    Err("'copy' negative copy range".to_string())
+                    Err(Error::with_operands(RangeCheck, vec![Object::Integer(n)]))
                 } else if len < unsigned_n {
-                    Err("'copy' stack too short".to_string())
+                    Err(Error::with_operands(
+                        StackUnderflow,
+                        vec![Object::Integer(n)],
+                    ))
                 } else {
                     let index = len - unsigned_n;
                     let tops: Vec<Object> = Vec::from(&self.main_stack[index..]);
@@ -187,19 +482,21 @@ impl Engine {
                     Ok(())
                 }
             }
-            Some(_) => Err("'copy', type mismatch".to_string()),
-            None => Err("'copy' stack underflow".to_string()),
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
         }
     }
 
-    pub fn index(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(Object::Integer(n)) => {
+    pub fn index(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(n)] => {
                 let (len, unsigned_n) = (self.main_stack.len(), n as usize);
                 if n < 0 {
-                    Err("'index' negative index".to_string())
+                    Err(Error::with_operands(RangeCheck, vec![Object::Integer(n)]))
                 } else if len <= unsigned_n {
-                    Err("'index' stack too short".to_string())
+                    Err(Error::with_operands(
+                        StackUnderflow,
+                        vec![Object::Integer(n)],
+                    ))
                 } else {
                     let index = len - unsigned_n - 1;
                     let element: Object = self.main_stack[index].clone();
@@ -207,269 +504,322 @@ impl Engine {
                     Ok(())
                 }
             }
-            Some(_) => Err("'copy', type mismatch".to_string()),
-            None => Err("'copy' stack underflow".to_string()),
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
         }
     }
 
-    pub fn pop(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(_) => Ok(()),
-            None => Err("'pop' stack underflow".to_string()),
-        }
+    pub fn pop(&mut self) -> Result<(), Error> {
+        self.pop_operands::<1>()?;
+        Ok(())
     }
 
-    pub fn exec(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(Object::Array(Executable, p)) => {
-                self.exec_stack.push(Box::new(OnceRunner::new(p)));
+    pub fn exec(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [o @ (Object::Array(Executable, _)
+            | Object::String(Executable, _)
+            | Object::File(Executable, _))]
+                if !o.is_executable() =>
+            {
+                Err(Error::with_operands(InvalidAccess, vec![o]))
+            }
+            [o @ (Object::Array(Executable, _)
+            | Object::String(Executable, _)
+            | Object::File(Executable, _))] => {
+                self.push_exec(o);
                 Ok(())
             }
-            Some(o) => self.process_object(o),
-            None => Err("'pop' stack underflow".to_string()),
+            [o] => self.process_object(o),
         }
     }
 
-    pub fn dup(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(i) => {
-                self.main_stack.push(i.clone());
-                self.main_stack.push(i.clone());
-                Ok(())
-            }
-            None => Err("'dup' stack underflow".to_string()),
-        }
+    pub fn dup(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        self.main_stack.push(a.clone());
+        self.main_stack.push(a);
+        Ok(())
     }
 
-    pub fn gt(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Bool(i2 > i1));
-                Ok(())
-            }
-            (Some(Object::Bool(b1)), Some(Object::Bool(b2))) => {
-                self.main_stack.push(Object::Bool(b2 & !b1));
+    pub fn eq(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        self.main_stack.push(Object::Bool(a.ps_eq(&b)));
+        Ok(())
+    }
+
+    pub fn ne(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        self.main_stack.push(Object::Bool(!a.ps_eq(&b)));
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.main_stack.clear();
+        Ok(())
+    }
+
+    pub fn clear_to_mark(&mut self) -> Result<(), Error> {
+        match self.mark_position() {
+            Some(index) => {
+                self.main_stack.truncate(index);
                 Ok(())
             }
-            (None, _) | (_, None) => Err("'gt' stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("'gt' not implemented".to_string()),
+            None => Err(Error::new(UnmatchedMark)),
         }
     }
 
-    pub fn eq(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Bool(i2 == i1));
+    pub fn count_to_mark(&mut self) -> Result<(), Error> {
+        match self.mark_position() {
+            Some(index) => {
+                let count = self.main_stack.len() - index - 1;
+                self.main_stack.push(Object::Integer(count as i64));
                 Ok(())
             }
-            (Some(Object::Bool(b1)), Some(Object::Bool(b2))) => {
-                self.main_stack.push(Object::Bool(b2 == b1));
-                Ok(())
-            }
-            (None, _) | (_, None) => Err("'eq' stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("'eq' not implemented".to_string()),
+            None => Err(Error::new(UnmatchedMark)),
         }
     }
 
-    pub fn ne(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Bool(i2 != i1));
-                Ok(())
+    pub fn build_array(&mut self) -> Result<Object, Error> {
+        match self.mark_position() {
+            Some(index)
+                if self.vm.is_global()
+                    && self.main_stack[index + 1..].iter().any(Object::is_local) =>
+            {
+                Err(Error::new(InvalidAccess))
             }
-            (Some(Object::Bool(b1)), Some(Object::Bool(b2))) => {
-                self.main_stack.push(Object::Bool(b2 != b1));
-                Ok(())
+            Some(index) => {
+                let array: Vec<Object> = self.main_stack.drain(index + 1..).collect();
+                self.main_stack.pop();
+                Ok(Object::Array(Literal, PsArray::new(array)))
             }
-            (None, _) | (_, None) => Err("'ne' stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("'ne' not implemented".to_string()),
+            None => Err(Error::new(UnmatchedMark)),
         }
     }
 
-    pub fn mul(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Integer(i1 * i2));
-                Ok(())
-            }
-            (None, _) | (_, None) => Err("stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("not implemented".to_string()),
-        }
+    fn mark_position(&self) -> Option<usize> {
+        self.main_stack
+            .iter()
+            .rposition(|object| matches!(object, Object::Mark))
     }
 
-    pub fn sub(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Integer(i2 - i1));
+    pub fn cond_if(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Bool(b), Object::Array(Executable, p)] => {
+                if b {
+                    self.exec_stack.call(&p);
+                }
                 Ok(())
             }
-            (None, _) | (_, None) => Err("stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("not implemented".to_string()),
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
         }
     }
 
-    pub fn div(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Integer(i2 / i1));
+    pub fn cond_ifelse(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Bool(b), Object::Array(Executable, pif), Object::Array(Executable, pelse)] => {
+                if b {
+                    self.exec_stack.call(&pif);
+                } else {
+                    self.exec_stack.call(&pelse);
+                }
                 Ok(())
             }
-            (None, _) | (_, None) => Err("stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("not implemented".to_string()),
+            [a, b, c] => Err(Error::with_operands(TypeCheck, vec![a, b, c])),
         }
     }
 
-    pub fn modulo(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Integer(i1)), Some(Object::Integer(i2))) => {
-                self.main_stack.push(Object::Integer(i2 % i1));
-                Ok(())
+    pub fn repeat(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(times), Object::Array(Executable, p)] => {
+                if times > 0 {
+                    self.exec_stack.push(Box::new(RepeatRunner::new(p, times)));
+                    Ok(())
+                } else if times == 0 {
+                    Ok(())
+                } else {
+                    Err(Error::with_operands(
+                        RangeCheck,
+                        vec![Object::Integer(times), Object::Array(Executable, p)],
+                    ))
+                }
             }
-            (None, _) | (_, None) => Err("stack underflow".to_string()),
-            (Some(_), Some(_)) => Err("not implemented".to_string()),
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
         }
     }
 
-    pub fn pop_and_print(&mut self) -> Result<(), String> {
-        if let Some(object) = self.main_stack.pop() {
-            println!("{object}");
-            Ok(())
-        } else {
-            Err("'=' stack underflow".to_string())
+    /// Default errordict procedure: record the error in $error and stop.
+    pub fn error_handler(&mut self, kind: ErrorKind) -> Result<(), Error> {
+        let [command] = self.pop_operands()?;
+        if !matches!(&self.pending_error, Some(error) if error.kind == kind) {
+            self.pending_error = Some(Error::new(kind).in_command(command.clone()));
         }
-    }
 
-    pub fn pstack(&mut self) -> Result<(), String> {
-        for object in self.main_stack.iter().rev() {
-            println!("{object} ")
+        let dollar_error = self.dict_stack.dollar_error();
+        dollar_error.insert("newerror".into(), Object::Bool(true));
+        dollar_error.insert(
+            "errorname".into(),
+            Object::Name(Literal, kind.name().into()),
+        );
+        dollar_error.insert("command".into(), command);
+        dollar_error.insert(
+            "ostack".into(),
+            Object::Array(Literal, PsArray::new(self.main_stack.clone())),
+        );
+
+        self.stop()
+    }
+
+    /// Default handleerror: report the error recorded in $error.
+    pub fn handleerror(&mut self) -> Result<(), Error> {
+        let dollar_error = self.dict_stack.dollar_error();
+        if let Some(Object::Bool(true)) = dollar_error.get(&"newerror".into()) {
+            dollar_error.insert("newerror".into(), Object::Bool(false));
+
+            let errorname = match dollar_error.get(&"errorname".into()) {
+                Some(Object::Name(_, name)) => name,
+                _ => "unknownerror".into(),
+            };
+            let command = dollar_error.get(&"command".into()).unwrap_or(Object::Null);
+            let ostack = match dollar_error.get(&"ostack".into()) {
+                Some(Object::Array(_, ostack)) => Some(ostack.objects().to_vec()),
+                _ => None,
+            };
+            let report = self.format_report(&errorname.to_string(), &command, ostack.as_deref());
+            self.write_bytes(report.as_bytes())?;
         }
         Ok(())
     }
 
-    pub fn clear(&mut self) -> Result<(), String> {
-        self.main_stack.clear();
-        Ok(())
-    }
+    /// The report of an error, as printed by handleerror.
+    fn format_report(
+        &self,
+        errorname: &str,
+        command: &Object,
+        ostack: Option<&[Object]>,
+    ) -> String {
+        let mut report = format!("Error: /{errorname} in {}", describe_command(command));
+        if let Some(position) = &self.position {
+            report.push_str(&format!(" at {position}"));
+        }
+        report.push('\n');
 
-    pub fn clear_to_mark(&mut self) -> Result<(), String> {
-        loop {
-            match self.main_stack.pop() {
-                Some(Object::Mark) => return Ok(()),
-                Some(_) => (),
-                None => return Err("'cleartomark' Mark not found".to_string()),
+        if let Some(ostack) = ostack {
+            report.push_str("Operand stack:\n");
+            for object in ostack.iter() {
+                let syntax = object.to_syntax();
+                report.push_str(&format!("    {}\n", String::from_utf8_lossy(&syntax)));
             }
         }
+        report
     }
 
-    pub fn count_to_mark(&mut self) -> Result<(), String> {
-        let mut count = 0;
-        let mut found = false;
-        for object in self.main_stack.iter().rev() {
-            match object {
-                Object::Mark => {
-                    found = true;
-                    break;
-                }
-                _ => count += 1,
-            }
-        }
-        if found {
-            self.main_stack.push(Object::Integer(count));
-            Ok(())
+    /// Unwind to the innermost stopped context. Outside of any, the whole
+    /// execution is aborted and the error being handled, if any, is
+    /// returned to the caller of the engine.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        if self.exec_stack.unwind_stopped() {
+            self.pending_error = None;
+            self.main_stack.push(Object::Bool(true));
         } else {
-            Err("'counttomark' Mark not found".to_string())
+            self.exec_stack.clear();
+            self.uncaught_error = self.pending_error.take();
         }
+        Ok(())
     }
 
-    pub fn build_array(&mut self) -> Result<Object, String> {
-        let mut array: Vec<Object> = Vec::new();
+    pub fn stopped(&mut self) -> Result<(), Error> {
+        let [object] = self.pop_operands()?;
+        self.exec_stack.push(Box::new(StoppedRunner::new()));
+        self.push_exec(object);
+        Ok(())
+    }
 
-        while let Some(object) = self.main_stack.pop() {
-            match object {
-                Object::Mark => return Ok(Object::Array(Literal, array)),
-                object => array.insert(0, object),
-            }
+    pub fn exit(&mut self) -> Result<(), Error> {
+        match self.exec_stack.unwind_loop() {
+            true => Ok(()),
+            false => Err(Error::new(InvalidExit)),
         }
-
-        Err("begin mark not found!".to_string())
     }
 
-    pub fn def(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (None, _) | (_, None) => Err("stack underflow".to_string()),
-            (Some(object), Some(Object::Name(Literal, n))) => {
-                self.dict_stack.def(n, object);
+    pub fn for_loop(&mut self) -> Result<(), Error> {
+        let operands = self.pop_operands()?;
+        let limit = match &operands[2] {
+            Object::Integer(i) => Some(*i as f64),
+            Object::Real(r) => Some(*r),
+            _ => None,
+        };
+        let control = match (&operands[0], &operands[1]) {
+            (Object::Integer(current), Object::Integer(increment)) => Some(ForControl::Integer {
+                current: Some(*current),
+                increment: *increment,
+            }),
+            (Object::Integer(current), Object::Real(increment)) => Some(ForControl::Real {
+                current: *current as f64,
+                increment: *increment,
+            }),
+            (Object::Real(current), Object::Integer(increment)) => Some(ForControl::Real {
+                current: *current,
+                increment: *increment as f64,
+            }),
+            (Object::Real(current), Object::Real(increment)) => Some(ForControl::Real {
+                current: *current,
+                increment: *increment,
+            }),
+            _ => None,
+        };
+
+        match (control, limit, operands) {
+            (Some(control), Some(limit), [_, _, _, Object::Array(Executable, p)]) => {
+                self.exec_stack
+                    .push(Box::new(ForRunner::new(p, control, limit)));
                 Ok(())
             }
-            _ => Err("wrong argument(s)".to_string()),
+            (_, _, operands) => Err(Error::with_operands(TypeCheck, operands.into())),
         }
     }
 
-    pub fn load(&mut self) -> Result<(), String> {
-        match self.main_stack.pop() {
-            Some(Object::Name(Literal, name)) => {
-                if let Some(object) = self.dict_stack.get(&name) {
-                    self.main_stack.push(object);
-                    Ok(())
-                } else {
-                    Err("name not found: {name".to_string())
-                }
+    pub fn loop_forever(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Array(Executable, p)] => {
+                self.exec_stack.push(Box::new(LoopRunner::new(p)));
+                Ok(())
             }
-            _ => Err("wrong argument".to_string()),
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
         }
     }
 
-    pub fn cond_if(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Array(Executable, p)), Some(Object::Bool(b))) => {
-                if b {
-                    self.exec_stack.push(Box::new(OnceRunner::new(p)));
-                }
-                Ok(())
-            }
-            (Some(a), Some(b)) => Err(format!("'if' wrong argument types {:?} {:?}", a, b)),
-            (None, _) | (_, None) => Err("'if' stack underflow".to_string()),
-        }
-    }
-
-    pub fn cond_ifelse(&mut self) -> Result<(), String> {
-        match (
-            self.main_stack.pop(),
-            self.main_stack.pop(),
-            self.main_stack.pop(),
-        ) {
-            (
-                Some(Object::Array(Executable, pelse)),
-                Some(Object::Array(Executable, pif)),
-                Some(Object::Bool(b)),
-            ) => {
-                if b {
-                    self.exec_stack.push(Box::new(OnceRunner::new(pif)));
-                } else {
-                    self.exec_stack.push(Box::new(OnceRunner::new(pelse)));
-                }
-                Ok(())
-            }
-            (Some(a), Some(b), Some(c)) => Err(format!(
-                "'ifelse' wrong argument types {:?} {:?} {:?}",
-                a, b, c
-            )),
-            (_, _, None) | (_, None, _) | (None, _, _) => {
-                Err("'ifelse' stack underflow".to_string())
-            }
+    pub fn forall(&mut self) -> Result<(), Error> {
+        let [object, proc] = self.pop_operands()?;
+        if !object.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![object, proc]));
         }
-    }
+        let (items, arity) = match &object {
+            Object::Array(_, array) => (array.clone(), 1),
+            Object::String(_, string) => (
+                string
+                    .bytes()
+                    .into_iter()
+                    .map(|b| Object::Integer(b as i64))
+                    .collect::<Vec<_>>()
+                    .into(),
+                1,
+            ),
+            Object::Dict(_, dict) => (
+                dict.entries()
+                    .into_iter()
+                    .flat_map(|(key, value)| [key.into_object(), value])
+                    .collect::<Vec<_>>()
+                    .into(),
+                2,
+            ),
+            _ => return Err(Error::with_operands(TypeCheck, vec![object, proc])),
+        };
 
-    pub fn repeat(&mut self) -> Result<(), String> {
-        match (self.main_stack.pop(), self.main_stack.pop()) {
-            (Some(Object::Array(Executable, p)), Some(Object::Integer(times))) => {
-                if times > 1 {
-                    self.exec_stack.push(Box::new(RepeatRunner::new(p, times)));
-                }
+        match proc {
+            Object::Array(Executable, p) => {
+                self.exec_stack
+                    .push(Box::new(ForallRunner::new(p, items, arity)));
                 Ok(())
             }
-            (Some(a), Some(b)) => Err(format!("'repeat' wrong argument types {:?} {:?}", a, b)),
-            (None, _) | (_, None) => Err("'repeat' stack underflow".to_string()),
+            proc => Err(Error::with_operands(TypeCheck, vec![object, proc])),
         }
     }
 }
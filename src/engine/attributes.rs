@@ -0,0 +1,95 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Access;
+use crate::Error;
+use crate::Name;
+use crate::Object;
+use crate::ObjectMode::*;
+
+impl Engine {
+    /// Push the type name of an object, as an executable name.
+    pub fn type_of(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        let name = Name::from(a.type_name());
+        self.main_stack.push(Object::Name(Executable, name));
+        Ok(())
+    }
+
+    pub fn cvx(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        self.main_stack.push(a.with_mode(Executable));
+        Ok(())
+    }
+
+    pub fn cvlit(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        self.main_stack.push(a.with_mode(Literal));
+        Ok(())
+    }
+
+    pub fn xcheck(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        self.main_stack
+            .push(Object::Bool(a.mode() == Some(Executable)));
+        Ok(())
+    }
+
+    pub fn rcheck(&mut self) -> Result<(), Error> {
+        self.check_access(Access::is_readable)
+    }
+
+    pub fn wcheck(&mut self) -> Result<(), Error> {
+        self.check_access(Access::is_writable)
+    }
+
+    fn check_access(&mut self, test: fn(Access) -> bool) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match a.access() {
+            Some(access) => {
+                self.main_stack.push(Object::Bool(test(access)));
+                Ok(())
+            }
+            None => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    pub fn readonly(&mut self) -> Result<(), Error> {
+        self.restrict_access(Access::ReadOnly)
+    }
+
+    pub fn executeonly(&mut self) -> Result<(), Error> {
+        self.restrict_access(Access::ExecuteOnly)
+    }
+
+    pub fn noaccess(&mut self) -> Result<(), Error> {
+        self.restrict_access(Access::NoAccess)
+    }
+
+    /// Reduce the access of a composite object. Arrays and strings get a new
+    /// reference with that access, dictionaries are changed in place.
+    fn restrict_access(&mut self, access: Access) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match a.access() {
+            Some(current) if access > current => {
+                return Err(Error::with_operands(InvalidAccess, vec![a]))
+            }
+            Some(_) => (),
+            None => return Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+
+        let restricted = match a {
+            Object::Array(m, array) => Object::Array(m, array.with_access(access)),
+            Object::String(m, string) => Object::String(m, string.with_access(access)),
+            Object::Dict(..) if access == Access::ExecuteOnly => {
+                return Err(Error::with_operands(TypeCheck, vec![a]))
+            }
+            Object::Dict(m, dict) => {
+                dict.set_access(access);
+                Object::Dict(m, dict)
+            }
+            other => other,
+        };
+        self.main_stack.push(restricted);
+        Ok(())
+    }
+}
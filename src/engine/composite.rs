@@ -0,0 +1,328 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Error;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::PsArray;
+use crate::PsString;
+
+/// Convert a PostScript index or count to `usize`, checking it against `len`
+/// (inclusive bound).
+fn checked_index(i: i64, len: usize) -> Option<usize> {
+    match usize::try_from(i) {
+        Ok(i) if i <= len => Some(i),
+        _ => None,
+    }
+}
+
+impl Engine {
+    pub fn length(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        if !a.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a]));
+        }
+        let length = match &a {
+            Object::Array(_, array) => array.len(),
+            Object::String(_, string) => string.len(),
+            Object::Dict(_, dict) => dict.len(),
+            Object::Name(_, name) => name.len(),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        self.main_stack.push(Object::Integer(length as i64));
+        Ok(())
+    }
+
+    pub fn get(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !a.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let object = match (&a, &b) {
+            (Object::Dict(_, dict), key) => match key.as_key() {
+                Some(key) => dict.get(&key),
+                None => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+            },
+            (Object::Array(_, array), Object::Integer(i)) => {
+                checked_index(*i, array.len()).and_then(|i| array.get(i))
+            }
+            (Object::String(_, string), Object::Integer(i)) => checked_index(*i, string.len())
+                .and_then(|i| string.get(i))
+                .map(|byte| Object::Integer(byte as i64)),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+
+        match object {
+            Some(object) => {
+                self.main_stack.push(object);
+                Ok(())
+            }
+            None if matches!(a, Object::Dict(..)) => {
+                Err(Error::with_operands(Undefined, vec![a, b]))
+            }
+            None => Err(Error::with_operands(RangeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn put(&mut self) -> Result<(), Error> {
+        let [a, b, c] = self.pop_operands()?;
+        if !a.is_writable() || (a.is_global() && c.is_local()) {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b, c]));
+        }
+        let done = match (&a, &b, &c) {
+            (Object::Dict(_, dict), key, object) => match key.as_key() {
+                Some(key) => {
+                    dict.insert(key, object.clone());
+                    Some(())
+                }
+                None => return Err(Error::with_operands(TypeCheck, vec![a, b, c])),
+            },
+            (Object::Array(_, array), Object::Integer(i), object) => {
+                checked_index(*i, array.len()).and_then(|i| array.put(i, object.clone()))
+            }
+            (Object::String(_, string), Object::Integer(i), Object::Integer(byte)) => {
+                match (checked_index(*i, string.len()), u8::try_from(*byte)) {
+                    (Some(i), Ok(byte)) => string.put(i, byte),
+                    _ => None,
+                }
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b, c])),
+        };
+
+        match done {
+            Some(()) => Ok(()),
+            None => Err(Error::with_operands(RangeCheck, vec![a, b, c])),
+        }
+    }
+
+    pub fn getinterval(&mut self) -> Result<(), Error> {
+        let [a, b, c] = self.pop_operands()?;
+        if !a.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b, c]));
+        }
+        let interval = match (&a, &b, &c) {
+            (Object::Array(m, array), Object::Integer(index), Object::Integer(count)) => {
+                match (
+                    checked_index(*index, array.len()),
+                    checked_index(*count, array.len()),
+                ) {
+                    (Some(index), Some(count)) => {
+                        array.interval(index, count).map(|a| Object::Array(*m, a))
+                    }
+                    _ => None,
+                }
+            }
+            (Object::String(m, string), Object::Integer(index), Object::Integer(count)) => {
+                match (
+                    checked_index(*index, string.len()),
+                    checked_index(*count, string.len()),
+                ) {
+                    (Some(index), Some(count)) => {
+                        string.interval(index, count).map(|s| Object::String(*m, s))
+                    }
+                    _ => None,
+                }
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b, c])),
+        };
+
+        match interval {
+            Some(interval) => {
+                self.main_stack.push(interval);
+                Ok(())
+            }
+            None => Err(Error::with_operands(RangeCheck, vec![a, b, c])),
+        }
+    }
+
+    pub fn putinterval(&mut self) -> Result<(), Error> {
+        let [a, b, c] = self.pop_operands()?;
+        if !a.is_writable() || !c.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b, c]));
+        }
+        let done = match (&a, &b, &c) {
+            (Object::Array(_, dest), Object::Integer(index), Object::Array(_, source)) => {
+                let objects = source.objects();
+                if dest.is_global() && objects.iter().any(Object::is_local) {
+                    return Err(Error::with_operands(InvalidAccess, vec![a, b, c]));
+                }
+                checked_index(*index, dest.len())
+                    .and_then(|index| dest.put_interval(index, &objects))
+            }
+            (Object::String(_, dest), Object::Integer(index), Object::String(_, source)) => {
+                checked_index(*index, dest.len())
+                    .and_then(|index| dest.put_interval(index, &source.bytes()))
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b, c])),
+        };
+
+        match done {
+            Some(()) => Ok(()),
+            None => Err(Error::with_operands(RangeCheck, vec![a, b, c])),
+        }
+    }
+
+    pub fn array(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(n)] => match usize::try_from(n) {
+                Ok(n) => {
+                    let array = PsArray::new(vec![Object::Null; n]);
+                    self.main_stack.push(Object::Array(Literal, array));
+                    Ok(())
+                }
+                Err(_) => Err(Error::with_operands(RangeCheck, vec![Object::Integer(n)])),
+            },
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Push all elements of an array, then the array itself.
+    pub fn aload(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_readable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::Array(m, array)] => {
+                self.main_stack.extend(array.objects());
+                self.main_stack.push(Object::Array(m, array));
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Fill an array with as many operands, the topmost going last.
+    pub fn astore(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_writable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::Array(m, array)] => {
+                let len = array.len();
+                if self.main_stack.len() < len {
+                    return Err(Error::with_operands(
+                        StackUnderflow,
+                        vec![Object::Array(m, array)],
+                    ));
+                }
+                let objects = &self.main_stack[self.main_stack.len() - len..];
+                if array.is_global() && objects.iter().any(Object::is_local) {
+                    return Err(Error::with_operands(
+                        InvalidAccess,
+                        vec![Object::Array(m, array)],
+                    ));
+                }
+                let objects = self.main_stack.split_off(self.main_stack.len() - len);
+                array.put_interval(0, &objects);
+                self.main_stack.push(Object::Array(m, array));
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// `copy` on composite objects: copy the first operand into the second
+    /// one and push the part of it that was overwritten, or the whole
+    /// dictionary.
+    pub(super) fn copy_composite(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !a.is_readable() || !b.is_writable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let stores_local = match &a {
+            Object::Array(_, source) => source.objects().iter().any(Object::is_local),
+            Object::Dict(_, source) => source.entries().iter().any(|(_, v)| v.is_local()),
+            _ => false,
+        };
+        if b.is_global() && stores_local {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let copied = match (&a, &b) {
+            (Object::Array(_, source), Object::Array(m, dest)) => dest
+                .put_interval(0, &source.objects())
+                .and_then(|_| dest.interval(0, source.len()))
+                .map(|interval| Object::Array(*m, interval)),
+            (Object::String(_, source), Object::String(m, dest)) => dest
+                .put_interval(0, &source.bytes())
+                .and_then(|_| dest.interval(0, source.len()))
+                .map(|interval| Object::String(*m, interval)),
+            (Object::Dict(_, source), Object::Dict(_, dest)) => {
+                for (key, value) in source.entries() {
+                    dest.insert(key, value);
+                }
+                Some(b.clone())
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+
+        match copied {
+            Some(copied) => {
+                self.main_stack.push(copied);
+                Ok(())
+            }
+            None => Err(Error::with_operands(RangeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn string(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(n)] => match usize::try_from(n) {
+                Ok(n) => {
+                    let string = PsString::new(vec![0; n]);
+                    self.main_stack.push(Object::String(Literal, string));
+                    Ok(())
+                }
+                Err(_) => Err(Error::with_operands(RangeCheck, vec![Object::Integer(n)])),
+            },
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Look for `seek` in `string`: on success push the parts following,
+    /// matching and preceding it, then true. Otherwise push `string` back,
+    /// then false.
+    pub fn search(&mut self) -> Result<(), Error> {
+        self.search_string(false)
+    }
+
+    /// As `search`, but only at the start of the string.
+    pub fn anchorsearch(&mut self) -> Result<(), Error> {
+        self.search_string(true)
+    }
+
+    fn search_string(&mut self, anchored: bool) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a, b] if !a.is_readable() || !b.is_readable() => {
+                Err(Error::with_operands(InvalidAccess, vec![a, b]))
+            }
+            [Object::String(m, string), Object::String(_, seek)] => {
+                let (haystack, needle) = (string.bytes(), seek.bytes());
+                let position = match anchored {
+                    true => haystack.starts_with(&needle).then_some(0),
+                    false if needle.is_empty() => Some(0),
+                    false => haystack
+                        .windows(needle.len())
+                        .position(|window| window == needle.as_slice()),
+                };
+
+                match position {
+                    Some(index) => {
+                        let end = index + needle.len();
+                        let part = |start, count| {
+                            Object::String(m, string.interval(start, count).unwrap())
+                        };
+                        let (post, found) =
+                            (part(end, string.len() - end), part(index, needle.len()));
+                        self.main_stack.push(post);
+                        self.main_stack.push(found);
+                        if !anchored {
+                            self.main_stack.push(part(0, index));
+                        }
+                        self.main_stack.push(Object::Bool(true));
+                    }
+                    None => {
+                        self.main_stack.push(Object::String(m, string));
+                        self.main_stack.push(Object::Bool(false));
+                    }
+                }
+                Ok(())
+            }
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::Engine;
+use crate::error::ErrorKind::{self, *};
+use crate::token::parse_number;
+use crate::Error;
+use crate::Name;
+use crate::Object;
+use crate::ObjectMode;
+use crate::PsString;
+
+/// A number operand, strings being parsed with the syntax of the lexer.
+fn number_operand(a: &Object) -> Result<Object, ErrorKind> {
+    match a {
+        Object::Integer(_) | Object::Real(_) => Ok(a.clone()),
+        Object::String(..) if !a.is_readable() => Err(InvalidAccess),
+        Object::String(_, s) => parse_number(&s.bytes()).ok_or(SyntaxError),
+        _ => Err(TypeCheck),
+    }
+}
+
+/// The digits of `n` in the given radix, upper case.
+fn radix_digits(mut n: u64, radix: u32) -> Vec<u8> {
+    let mut digits = Vec::new();
+    loop {
+        let digit = char::from_digit((n % radix as u64) as u32, radix).unwrap();
+        digits.push(digit.to_ascii_uppercase() as u8);
+        n /= radix as u64;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+impl Engine {
+    pub fn cvi(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match number_operand(&a) {
+            Ok(Object::Integer(i)) => {
+                self.main_stack.push(Object::Integer(i));
+                Ok(())
+            }
+            Ok(Object::Real(r)) if r.trunc() >= i64::MIN as f64 && r.trunc() <= i64::MAX as f64 => {
+                self.main_stack.push(Object::Integer(r.trunc() as i64));
+                Ok(())
+            }
+            Ok(_) => Err(Error::with_operands(RangeCheck, vec![a])),
+            Err(kind) => Err(Error::with_operands(kind, vec![a])),
+        }
+    }
+
+    pub fn cvr(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match number_operand(&a) {
+            Ok(number) => {
+                self.main_stack
+                    .push(Object::Real(number.as_real().unwrap()));
+                Ok(())
+            }
+            Err(kind) => Err(Error::with_operands(kind, vec![a])),
+        }
+    }
+
+    /// Convert a string to a name, keeping its literal/executable attribute.
+    pub fn cvn(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_readable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::String(m, s)] => {
+                let name = Name::new(&s.bytes());
+                self.main_stack.push(Object::Name(m, name));
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Write the text of any object in a string and push the part of it
+    /// that was filled.
+    pub fn cvs(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !a.is_readable() || !b.is_writable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        match &b {
+            Object::String(m, dest) => match store_text(&a.to_text(), *m, dest) {
+                Some(text) => {
+                    self.main_stack.push(text);
+                    Ok(())
+                }
+                None => Err(Error::with_operands(RangeCheck, vec![a, b])),
+            },
+            _ => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    /// As `cvs` for numbers, in a radix from 2 to 36. Unless the radix is
+    /// 10, reals are truncated and negative integers are written in two's
+    /// complement.
+    pub fn cvrs(&mut self) -> Result<(), Error> {
+        let [a, b, c] = self.pop_operands()?;
+        if !c.is_writable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b, c]));
+        }
+        let (radix, m, dest) = match (&a, &b, &c) {
+            (
+                Object::Integer(_) | Object::Real(_),
+                Object::Integer(radix),
+                Object::String(m, dest),
+            ) => (*radix, *m, dest),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b, c])),
+        };
+
+        let text = match (&a, radix) {
+            (_, 10) => Some(a.to_text()),
+            (_, 2..=36) => match a {
+                Object::Integer(i) => Some(i),
+                Object::Real(r) if r.trunc() >= i64::MIN as f64 && r.trunc() <= i64::MAX as f64 => {
+                    Some(r.trunc() as i64)
+                }
+                _ => None,
+            }
+            .map(|i| radix_digits(i as u64, radix as u32)),
+            _ => None,
+        };
+
+        match text.and_then(|text| store_text(&text, m, dest)) {
+            Some(text) => {
+                self.main_stack.push(text);
+                Ok(())
+            }
+            None => Err(Error::with_operands(RangeCheck, vec![a, b, c])),
+        }
+    }
+}
+
+/// Copy `text` at the start of `dest` and return the part of it that was
+/// filled, or `None` if it does not fit.
+fn store_text(text: &[u8], m: ObjectMode, dest: &PsString) -> Option<Object> {
+    dest.put_interval(0, text)?;
+    dest.interval(0, text.len()).map(|s| Object::String(m, s))
+}
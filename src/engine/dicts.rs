@@ -0,0 +1,226 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Access;
+use crate::Dict;
+use crate::Error;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::PsArray;
+
+impl Engine {
+    pub fn dict(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(n)] => match usize::try_from(n) {
+                Ok(capacity) => {
+                    self.main_stack
+                        .push(Object::Dict(Literal, Dict::with_capacity(capacity)));
+                    Ok(())
+                }
+                Err(_) => Err(Error::with_operands(RangeCheck, vec![Object::Integer(n)])),
+            },
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// `>>`: build a dictionary from the key/value pairs above the mark.
+    pub fn enddict(&mut self) -> Result<(), Error> {
+        let index = match self.mark_position() {
+            Some(index) => index,
+            None => return Err(Error::new(UnmatchedMark)),
+        };
+        let pairs = &self.main_stack[index + 1..];
+        if pairs.len() % 2 == 1 {
+            return Err(Error::new(RangeCheck));
+        }
+        if self.vm.is_global() && pairs.iter().any(Object::is_local) {
+            return Err(Error::new(InvalidAccess));
+        }
+
+        let dict = Dict::with_capacity(pairs.len() / 2);
+        for pair in pairs.chunks(2) {
+            match pair[0].as_key() {
+                Some(key) => dict.insert(key, pair[1].clone()),
+                None => return Err(Error::new(TypeCheck)),
+            }
+        }
+
+        self.main_stack.truncate(index);
+        self.main_stack.push(Object::Dict(Literal, dict));
+        Ok(())
+    }
+
+    pub fn begin(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_readable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::Dict(_, dict)] => {
+                self.dict_stack.begin(dict);
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.dict_stack.end() {
+            Some(_) => Ok(()),
+            None => Err(Error::new(DictStackUnderflow)),
+        }
+    }
+
+    /// Define `key` in the current dictionary.
+    pub fn def(&mut self) -> Result<(), Error> {
+        let [key, object] = self.pop_operands()?;
+        match key.as_key() {
+            Some(_) if !self.dict_stack.current().access().is_writable() => {
+                Err(Error::with_operands(InvalidAccess, vec![key, object]))
+            }
+            Some(_) if self.dict_stack.current().is_global() && object.is_local() => {
+                Err(Error::with_operands(InvalidAccess, vec![key, object]))
+            }
+            Some(name) => {
+                self.dict_stack.def(name, object);
+                Ok(())
+            }
+            None => Err(Error::with_operands(TypeCheck, vec![key, object])),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<(), Error> {
+        let [key] = self.pop_operands()?;
+        match key.as_key().map(|name| self.dict_stack.get(&name)) {
+            Some(Some(object)) => {
+                self.main_stack.push(object);
+                Ok(())
+            }
+            Some(None) => Err(Error::with_operands(Undefined, vec![key])),
+            None => Err(Error::with_operands(TypeCheck, vec![key])),
+        }
+    }
+
+    pub fn known(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        match (&a, b.as_key()) {
+            (Object::Dict(_, dict), Some(_)) if !dict.access().is_readable() => {
+                Err(Error::with_operands(InvalidAccess, vec![a, b]))
+            }
+            (Object::Dict(_, dict), Some(key)) => {
+                self.main_stack.push(Object::Bool(dict.contains_key(&key)));
+                Ok(())
+            }
+            _ => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    /// Push the topmost dictionary defining the key and true, or false.
+    pub fn where_defined(&mut self) -> Result<(), Error> {
+        let [key] = self.pop_operands()?;
+        match key.as_key() {
+            Some(name) => {
+                match self.dict_stack.find(&name) {
+                    Some(dict) => {
+                        self.main_stack.push(Object::Dict(Literal, dict));
+                        self.main_stack.push(Object::Bool(true));
+                    }
+                    None => self.main_stack.push(Object::Bool(false)),
+                }
+                Ok(())
+            }
+            None => Err(Error::with_operands(TypeCheck, vec![key])),
+        }
+    }
+
+    pub fn undef(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        match (&a, b.as_key()) {
+            (Object::Dict(_, dict), Some(_)) if !dict.access().is_writable() => {
+                Err(Error::with_operands(InvalidAccess, vec![a, b]))
+            }
+            (Object::Dict(_, dict), Some(key)) => {
+                dict.remove(&key);
+                Ok(())
+            }
+            _ => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn currentdict(&mut self) -> Result<(), Error> {
+        let dict = self.dict_stack.current();
+        self.main_stack.push(Object::Dict(Literal, dict));
+        Ok(())
+    }
+
+    pub fn countdictstack(&mut self) -> Result<(), Error> {
+        let count = self.dict_stack.len();
+        self.main_stack.push(Object::Integer(count as i64));
+        Ok(())
+    }
+
+    /// Store the dictionary stack, bottom first, in the given array and push
+    /// the part of it that was filled.
+    pub fn dictstack(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_writable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::Array(m, array)] => {
+                let dicts: Vec<Object> = self
+                    .dict_stack
+                    .dicts()
+                    .into_iter()
+                    .map(|dict| Object::Dict(Literal, dict))
+                    .collect();
+                match array.put_interval(0, &dicts) {
+                    Some(()) => {
+                        let filled: PsArray = array.interval(0, dicts.len()).unwrap();
+                        self.main_stack.push(Object::Array(m, filled));
+                        Ok(())
+                    }
+                    None => Err(Error::with_operands(
+                        RangeCheck,
+                        vec![Object::Array(m, array)],
+                    )),
+                }
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Replace the executable names of a procedure which designate operators
+    /// by the operators themselves, so that they are no more looked up. Nested
+    /// procedures are bound too and made read-only; read-only procedures are
+    /// left as they are.
+    pub fn bind(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match &a {
+            Object::Array(_, proc) => self.bind_proc(proc, &mut Vec::new()),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+        self.main_stack.push(a);
+        Ok(())
+    }
+
+    /// Bind `proc` unless already in `bound`, which guards against
+    /// procedures containing themselves.
+    fn bind_proc(&self, proc: &PsArray, bound: &mut Vec<PsArray>) {
+        if !proc.access().is_writable() || bound.iter().any(|p| p.ptr_eq(proc)) {
+            return;
+        }
+        bound.push(proc.clone());
+
+        for (i, object) in proc.objects().into_iter().enumerate() {
+            match object {
+                Object::Name(Executable, name) => {
+                    if let Some(op @ Object::Operator(Executable, _)) =
+                        self.dict_stack.get(&name.into())
+                    {
+                        proc.put(i, op);
+                    }
+                }
+                Object::Array(Executable, inner) if inner.access().is_writable() => {
+                    self.bind_proc(&inner, bound);
+                    let inner = inner.with_access(Access::ReadOnly);
+                    proc.put(i, Object::Array(Executable, inner));
+                }
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use super::Engine;
+use crate::error::ErrorKind::{self, *};
+use crate::xstack::FileRunner;
+use crate::Error;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::PsFile;
+use log::debug;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Cursor};
+
+fn open_error(name: &str, e: io::Error) -> ErrorKind {
+    debug!("error on opening {name}: {e}");
+    match e.kind() {
+        io::ErrorKind::NotFound => UndefinedFileName,
+        io::ErrorKind::PermissionDenied => InvalidFileAccess,
+        _ => IoError,
+    }
+}
+
+/// Open a file of the file system: for reading with access `r`, writing with
+/// `w` and appending with `a`.
+fn open(name: &str, access: &[u8]) -> Result<PsFile, ErrorKind> {
+    let mut options = OpenOptions::new();
+    match access {
+        b"r" => options.read(true),
+        b"w" => options.write(true).create(true).truncate(true),
+        b"a" => options.append(true).create(true),
+        _ => return Err(InvalidFileAccess),
+    };
+    let file: File = options.open(name).map_err(|e| open_error(name, e))?;
+    Ok(match access {
+        b"r" => PsFile::reader(name, Box::new(BufReader::new(file))),
+        _ => PsFile::writer(name, Box::new(BufWriter::new(file))),
+    })
+}
+
+impl Engine {
+    /// Open a file, the special files `%stdin`, `%stdout` and `%stderr`
+    /// being the engine's ones.
+    pub fn file(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !a.is_readable() || !b.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let (name, access) = match (&a, &b) {
+            (Object::String(_, name), Object::String(_, access)) => (name.bytes(), access.bytes()),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+        let name = String::from_utf8_lossy(&name).into_owned();
+
+        let file = match (name.as_str(), access.as_slice()) {
+            ("%stdin", b"r") => Ok(self.stdin.clone()),
+            ("%stdout", b"w" | b"a") => Ok(self.stdout.clone()),
+            ("%stderr", b"w" | b"a") => Ok(self.stderr.clone()),
+            ("%stdin" | "%stdout" | "%stderr", _) => Err(InvalidFileAccess),
+            (name, access) => open(name, access),
+        };
+        match file {
+            Ok(file) => {
+                self.main_stack.push(Object::File(Literal, file));
+                Ok(())
+            }
+            Err(kind) => Err(Error::with_operands(kind, vec![a, b])),
+        }
+    }
+
+    /// Push the next byte of a file and true, or false at the end of it.
+    pub fn read(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        let result = match &a {
+            Object::File(_, file) => file.read_byte(),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        match result {
+            Ok(Some(byte)) => {
+                self.main_stack.push(Object::Integer(byte as i64));
+                self.main_stack.push(Object::Bool(true));
+                Ok(())
+            }
+            Ok(None) => {
+                self.main_stack.push(Object::Bool(false));
+                Ok(())
+            }
+            Err(error) => Err(Error::with_operands(error.kind, vec![a])),
+        }
+    }
+
+    /// Write a byte, the low-order 8 bits of an integer, to a file.
+    pub fn write(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        let result = match (&a, &b) {
+            (Object::File(_, file), Object::Integer(i)) => file.write(&[*i as u8]),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+        result.map_err(|error| Error::with_operands(error.kind, vec![a, b]))
+    }
+
+    pub fn writestring(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !b.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let result = match (&a, &b) {
+            (Object::File(_, file), Object::String(_, string)) => file.write(&string.bytes()),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+        result.map_err(|error| Error::with_operands(error.kind, vec![a, b]))
+    }
+
+    /// Read a line into a string and push the part of it that was filled,
+    /// then whether a newline was met before the end of the file.
+    pub fn readline(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !b.is_writable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let (file, m, string) = match (&a, &b) {
+            (Object::File(_, file), Object::String(m, string)) => (file, *m, string),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+
+        let (line, newline) = match file.read_line() {
+            Ok(result) => result,
+            Err(error) => return Err(Error::with_operands(error.kind, vec![a, b])),
+        };
+        match string.put_interval(0, &line) {
+            Some(()) => {
+                let line = string.interval(0, line.len()).unwrap();
+                self.main_stack.push(Object::String(m, line));
+                self.main_stack.push(Object::Bool(newline));
+                Ok(())
+            }
+            None => Err(Error::with_operands(RangeCheck, vec![a, b])),
+        }
+    }
+
+    /// Fill a string from a file and push the part of it that was filled,
+    /// then whether it is the whole string.
+    pub fn readstring(&mut self) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        if !b.is_writable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a, b]));
+        }
+        let (file, m, string) = match (&a, &b) {
+            (Object::File(_, file), Object::String(m, string)) => (file, *m, string),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+        };
+        if string.is_empty() {
+            return Err(Error::with_operands(RangeCheck, vec![a, b]));
+        }
+
+        let bytes = match file.read_bytes(string.len()) {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(Error::with_operands(error.kind, vec![a, b])),
+        };
+        string.put_interval(0, &bytes);
+        let filled = string.interval(0, bytes.len()).unwrap();
+        self.main_stack.push(Object::String(m, filled));
+        self.main_stack
+            .push(Object::Bool(bytes.len() == string.len()));
+        Ok(())
+    }
+
+    /// Push the number of bytes which can be read from a file without
+    /// waiting, or -1 at the end of it.
+    pub fn bytesavailable(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::File(_, file)] => {
+                let available = file.bytes_available().map_or(-1, |n| n as i64);
+                self.main_stack.push(Object::Integer(available));
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    pub fn flushfile(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        let result = match &a {
+            Object::File(_, file) => file.flush(),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        result.map_err(|error| Error::with_operands(error.kind, vec![a]))
+    }
+
+    pub fn closefile(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        let result = match &a {
+            Object::File(_, file) => file.close(),
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        result.map_err(|error| Error::with_operands(error.kind, vec![a]))
+    }
+
+    /// Push the innermost file being executed, or a closed file if none.
+    pub fn currentfile(&mut self) -> Result<(), Error> {
+        let file = self
+            .exec_stack
+            .current_file()
+            .unwrap_or_else(|| PsFile::closed("%invalid"));
+        self.main_stack.push(Object::File(Literal, file));
+        Ok(())
+    }
+
+    /// Execute the PostScript program of the named file.
+    pub fn run(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        if !a.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a]));
+        }
+        let file = match &a {
+            Object::String(_, name) => {
+                let name = String::from_utf8_lossy(&name.bytes()).into_owned();
+                open(&name, b"r")
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        match file {
+            Ok(file) => {
+                self.exec_stack.push(Box::new(FileRunner::new(file)));
+                Ok(())
+            }
+            Err(kind) => Err(Error::with_operands(kind, vec![a])),
+        }
+    }
+
+    /// Read one object from a file or from the start of a string, as the
+    /// interpreter would: push it and true, the rest of the string coming
+    /// first, or only false at the end of the source.
+    pub fn token(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        if !a.is_readable() {
+            return Err(Error::with_operands(InvalidAccess, vec![a]));
+        }
+        let file = match &a {
+            Object::File(_, file) => file.clone(),
+            Object::String(_, string) => {
+                PsFile::reader("%string", Box::new(Cursor::new(string.bytes())))
+            }
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+
+        let object = match FileRunner::new(file.clone()).next_object(&self.dict_stack) {
+            None => {
+                self.main_stack.push(Object::Bool(false));
+                return Ok(());
+            }
+            Some(Ok((object, _))) => object,
+            Some(Err(error)) => return Err(Error::with_operands(error.kind, vec![a])),
+        };
+        if let Object::String(m, string) = &a {
+            let rest = match file.read_bytes(string.len()) {
+                Ok(rest) => rest,
+                Err(error) => return Err(Error::with_operands(error.kind, vec![a])),
+            };
+            let post = string.interval(string.len() - rest.len(), rest.len());
+            self.main_stack.push(Object::String(*m, post.unwrap()));
+        }
+        self.main_stack.push(object);
+        self.main_stack.push(Object::Bool(true));
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Error;
+use crate::Object;
+
+use std::cmp::Ordering;
+
+impl Engine {
+    /// Apply a binary arithmetic operator. Integer operands give an integer
+    /// result unless it overflows, in which case it is promoted to real.
+    fn arithmetic(
+        &mut self,
+        int_op: fn(i64, i64) -> Option<i64>,
+        real_op: fn(f64, f64) -> f64,
+    ) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        let result = match (&a, &b) {
+            (Object::Integer(i1), Object::Integer(i2)) => match int_op(*i1, *i2) {
+                Some(i) => Object::Integer(i),
+                None => Object::Real(real_op(*i1 as f64, *i2 as f64)),
+            },
+            _ => match (a.as_real(), b.as_real()) {
+                (Some(r1), Some(r2)) => Object::Real(real_op(r1, r2)),
+                _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+            },
+        };
+        self.push_number(result, vec![a, b])
+    }
+
+    /// Apply a unary operator preserving the operand type, integers being
+    /// promoted to real on overflow.
+    fn unary(
+        &mut self,
+        int_op: fn(i64) -> Option<i64>,
+        real_op: fn(f64) -> f64,
+    ) -> Result<(), Error> {
+        let result = match self.pop_operands()? {
+            [Object::Integer(i)] => match int_op(i) {
+                Some(result) => Object::Integer(result),
+                None => Object::Real(real_op(i as f64)),
+            },
+            [Object::Real(r)] => Object::Real(real_op(r)),
+            [a] => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+        self.main_stack.push(result);
+        Ok(())
+    }
+
+    /// Apply a real function defined on `domain`, out of which it raises a
+    /// rangecheck.
+    fn real_function(&mut self, domain: fn(f64) -> bool, op: fn(f64) -> f64) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        match a.as_real() {
+            Some(r) if domain(r) => self.push_number(Object::Real(op(r)), vec![a]),
+            Some(_) => Err(Error::with_operands(RangeCheck, vec![a])),
+            None => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Push a computed number, unless it is not a finite one.
+    fn push_number(&mut self, result: Object, operands: Vec<Object>) -> Result<(), Error> {
+        match result {
+            Object::Real(r) if !r.is_finite() => {
+                Err(Error::with_operands(UndefinedResult, operands))
+            }
+            result => {
+                self.main_stack.push(result);
+                Ok(())
+            }
+        }
+    }
+
+    fn compare(&mut self, test: fn(Ordering) -> bool) -> Result<(), Error> {
+        let [a, b] = self.pop_operands()?;
+        let ordering = match (&a, &b) {
+            (Object::Integer(i1), Object::Integer(i2)) => Some(i1.cmp(i2)),
+            (Object::Bool(b1), Object::Bool(b2)) => Some(b1.cmp(b2)),
+            (Object::String(_, s1), Object::String(_, s2)) => Some(s1.bytes().cmp(&s2.bytes())),
+            _ => match (a.as_real(), b.as_real()) {
+                (Some(r1), Some(r2)) => r1.partial_cmp(&r2),
+                _ => return Err(Error::with_operands(TypeCheck, vec![a, b])),
+            },
+        };
+        match ordering {
+            Some(ordering) => {
+                self.main_stack.push(Object::Bool(test(ordering)));
+                Ok(())
+            }
+            None => Err(Error::with_operands(UndefinedResult, vec![a, b])),
+        }
+    }
+
+    pub fn add(&mut self) -> Result<(), Error> {
+        self.arithmetic(i64::checked_add, |r1, r2| r1 + r2)
+    }
+
+    pub fn sub(&mut self) -> Result<(), Error> {
+        self.arithmetic(i64::checked_sub, |r1, r2| r1 - r2)
+    }
+
+    pub fn mul(&mut self) -> Result<(), Error> {
+        self.arithmetic(i64::checked_mul, |r1, r2| r1 * r2)
+    }
+
+    /// `div` always yields a real.
+    pub fn div(&mut self) -> Result<(), Error> {
+        self.arithmetic(|_, _| None, |r1, r2| r1 / r2)
+    }
+
+    pub fn idiv(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(i1), Object::Integer(i2)] => match i1.checked_div(i2) {
+                Some(i) => {
+                    self.main_stack.push(Object::Integer(i));
+                    Ok(())
+                }
+                None => Err(Error::with_operands(
+                    UndefinedResult,
+                    vec![Object::Integer(i1), Object::Integer(i2)],
+                )),
+            },
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn modulo(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(i1), Object::Integer(i2)] => match i1.checked_rem(i2) {
+                Some(i) => {
+                    self.main_stack.push(Object::Integer(i));
+                    Ok(())
+                }
+                None => Err(Error::with_operands(
+                    UndefinedResult,
+                    vec![Object::Integer(i1), Object::Integer(i2)],
+                )),
+            },
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn neg(&mut self) -> Result<(), Error> {
+        self.unary(i64::checked_neg, |r| -r)
+    }
+
+    pub fn abs(&mut self) -> Result<(), Error> {
+        self.unary(i64::checked_abs, f64::abs)
+    }
+
+    pub fn ceiling(&mut self) -> Result<(), Error> {
+        self.unary(Some, f64::ceil)
+    }
+
+    pub fn floor(&mut self) -> Result<(), Error> {
+        self.unary(Some, f64::floor)
+    }
+
+    /// Halfway values are rounded up, as in PostScript: -2.5 gives -2.
+    pub fn round(&mut self) -> Result<(), Error> {
+        self.unary(Some, |r| (r + 0.5).floor())
+    }
+
+    pub fn truncate(&mut self) -> Result<(), Error> {
+        self.unary(Some, f64::trunc)
+    }
+
+    pub fn gt(&mut self) -> Result<(), Error> {
+        self.compare(Ordering::is_gt)
+    }
+
+    pub fn ge(&mut self) -> Result<(), Error> {
+        self.compare(Ordering::is_ge)
+    }
+
+    pub fn lt(&mut self) -> Result<(), Error> {
+        self.compare(Ordering::is_lt)
+    }
+
+    pub fn le(&mut self) -> Result<(), Error> {
+        self.compare(Ordering::is_le)
+    }
+
+    pub fn sqrt(&mut self) -> Result<(), Error> {
+        self.real_function(|r| r >= 0.0, f64::sqrt)
+    }
+
+    pub fn ln(&mut self) -> Result<(), Error> {
+        self.real_function(|r| r > 0.0, f64::ln)
+    }
+
+    pub fn log(&mut self) -> Result<(), Error> {
+        self.real_function(|r| r > 0.0, f64::log10)
+    }
+
+    /// Angles are in degrees.
+    pub fn sin(&mut self) -> Result<(), Error> {
+        self.real_function(|_| true, |r| r.to_radians().sin())
+    }
+
+    pub fn cos(&mut self) -> Result<(), Error> {
+        self.real_function(|_| true, |r| r.to_radians().cos())
+    }
+
+    pub fn exp(&mut self) -> Result<(), Error> {
+        let [base, exponent] = self.pop_operands()?;
+        match (base.as_real(), exponent.as_real()) {
+            (Some(b), Some(e)) => self.push_number(Object::Real(b.powf(e)), vec![base, exponent]),
+            _ => Err(Error::with_operands(TypeCheck, vec![base, exponent])),
+        }
+    }
+
+    /// Angle in degrees, between 0 and 360, of the vector (den, num).
+    pub fn atan(&mut self) -> Result<(), Error> {
+        let [num, den] = self.pop_operands()?;
+        match (num.as_real(), den.as_real()) {
+            (Some(n), Some(d)) if n == 0.0 && d == 0.0 => {
+                Err(Error::with_operands(UndefinedResult, vec![num, den]))
+            }
+            (Some(n), Some(d)) => {
+                let angle = n.atan2(d).to_degrees();
+                let angle = if angle < 0.0 { angle + 360.0 } else { angle };
+                self.main_stack.push(Object::Real(angle));
+                Ok(())
+            }
+            _ => Err(Error::with_operands(TypeCheck, vec![num, den])),
+        }
+    }
+
+    /// Apply a logical operator to booleans, or bitwise to integers.
+    fn logical(
+        &mut self,
+        bool_op: fn(bool, bool) -> bool,
+        int_op: fn(i64, i64) -> i64,
+    ) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Bool(b1), Object::Bool(b2)] => {
+                self.main_stack.push(Object::Bool(bool_op(b1, b2)));
+                Ok(())
+            }
+            [Object::Integer(i1), Object::Integer(i2)] => {
+                self.main_stack.push(Object::Integer(int_op(i1, i2)));
+                Ok(())
+            }
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+
+    pub fn and(&mut self) -> Result<(), Error> {
+        self.logical(|b1, b2| b1 & b2, |i1, i2| i1 & i2)
+    }
+
+    pub fn or(&mut self) -> Result<(), Error> {
+        self.logical(|b1, b2| b1 | b2, |i1, i2| i1 | i2)
+    }
+
+    pub fn xor(&mut self) -> Result<(), Error> {
+        self.logical(|b1, b2| b1 ^ b2, |i1, i2| i1 ^ i2)
+    }
+
+    pub fn not(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Bool(b)] => {
+                self.main_stack.push(Object::Bool(!b));
+                Ok(())
+            }
+            [Object::Integer(i)] => {
+                self.main_stack.push(Object::Integer(!i));
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Shift left for a positive shift, right otherwise. Bits shifted out
+    /// are lost and zeros are shifted in.
+    pub fn bitshift(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Integer(i), Object::Integer(shift)] => {
+                let bits = i as u64;
+                let result = match shift {
+                    0..=63 => bits << shift,
+                    -63..=-1 => bits >> -shift,
+                    _ => 0,
+                };
+                self.main_stack.push(Object::Integer(result as i64));
+                Ok(())
+            }
+            [a, b] => Err(Error::with_operands(TypeCheck, vec![a, b])),
+        }
+    }
+}
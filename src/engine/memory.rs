@@ -0,0 +1,58 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Error;
+use crate::Object;
+
+impl Engine {
+    /// Push a save object, a snapshot of the current state of the virtual
+    /// memory.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let id = self.vm.save();
+        self.main_stack.push(Object::Save(id));
+        Ok(())
+    }
+
+    /// Undo the modifications of local arrays and dictionaries made since a
+    /// save. Local objects created since may not be left on the operand or
+    /// dictionary stacks, and a save may only be restored once.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let [a] = self.pop_operands()?;
+        let id = match a {
+            Object::Save(id) => id,
+            _ => return Err(Error::with_operands(TypeCheck, vec![a])),
+        };
+
+        let created_since = |birth: u64| birth >= id;
+        let invalid = !self.vm.is_valid(id)
+            || self
+                .main_stack
+                .iter()
+                .any(|object| object.is_local() && object.birth().is_some_and(created_since))
+            || self
+                .dict_stack
+                .dicts()
+                .iter()
+                .any(|dict| !dict.is_global() && created_since(dict.birth()));
+        if invalid {
+            return Err(Error::with_operands(InvalidRestore, vec![a]));
+        }
+        self.vm.restore(id);
+        Ok(())
+    }
+
+    /// Allocate composite objects in global VM if true, local VM otherwise.
+    pub fn setglobal(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [Object::Bool(global)] => {
+                self.vm.set_global(global);
+                Ok(())
+            }
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    pub fn currentglobal(&mut self) -> Result<(), Error> {
+        self.main_stack.push(Object::Bool(self.vm.is_global()));
+        Ok(())
+    }
+}
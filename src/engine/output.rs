@@ -0,0 +1,68 @@
+use super::Engine;
+use crate::error::ErrorKind::*;
+use crate::Error;
+use crate::Object;
+
+impl Engine {
+    /// Write to the standard output.
+    pub(super) fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.stdout.write(bytes)
+    }
+
+    /// Flush the standard output.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.stdout.flush()
+    }
+
+    /// `=`: print the text of an object, as `cvs` makes it, and a newline.
+    pub fn pop_and_print(&mut self) -> Result<(), Error> {
+        let [object] = self.pop_operands()?;
+        self.write_bytes(&object.to_text())?;
+        self.write_bytes(b"\n")?;
+        Ok(())
+    }
+
+    pub fn pop_and_print_only(&mut self) -> Result<(), Error> {
+        let [object] = self.pop_operands()?;
+        self.write_bytes(&object.to_text())
+    }
+
+    /// `==`: print an object in PostScript syntax, and a newline.
+    pub fn pop_and_print_syntax(&mut self) -> Result<(), Error> {
+        let [object] = self.pop_operands()?;
+        self.write_bytes(&object.to_syntax())?;
+        self.write_bytes(b"\n")?;
+        Ok(())
+    }
+
+    pub fn pop_and_print_syntax_only(&mut self) -> Result<(), Error> {
+        let [object] = self.pop_operands()?;
+        self.write_bytes(&object.to_syntax())
+    }
+
+    pub fn print(&mut self) -> Result<(), Error> {
+        match self.pop_operands()? {
+            [a] if !a.is_readable() => Err(Error::with_operands(InvalidAccess, vec![a])),
+            [Object::String(_, s)] => self.write_bytes(&s.bytes()),
+            [a] => Err(Error::with_operands(TypeCheck, vec![a])),
+        }
+    }
+
+    /// Print the operand stack, topmost first, as `=` does.
+    pub fn stack(&mut self) -> Result<(), Error> {
+        for object in self.main_stack.clone().iter().rev() {
+            self.write_bytes(&object.to_text())?;
+            self.write_bytes(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Print the operand stack, topmost first, as `==` does.
+    pub fn pstack(&mut self) -> Result<(), Error> {
+        for object in self.main_stack.clone().iter().rev() {
+            self.write_bytes(&object.to_syntax())?;
+            self.write_bytes(b"\n")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::Object;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    DictFull,
+    DictStackOverflow,
+    DictStackUnderflow,
+    ExecStackOverflow,
+    Interrupt,
+    InvalidAccess,
+    InvalidExit,
+    InvalidFileAccess,
+    InvalidRestore,
+    IoError,
+    LimitCheck,
+    RangeCheck,
+    StackOverflow,
+    StackUnderflow,
+    SyntaxError,
+    TypeCheck,
+    Undefined,
+    UndefinedFileName,
+    UndefinedResult,
+    UnmatchedMark,
+    Unregistered,
+    VmError,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::DictFull => "dictfull",
+            ErrorKind::DictStackOverflow => "dictstackoverflow",
+            ErrorKind::DictStackUnderflow => "dictstackunderflow",
+            ErrorKind::ExecStackOverflow => "execstackoverflow",
+            ErrorKind::Interrupt => "interrupt",
+            ErrorKind::InvalidAccess => "invalidaccess",
+            ErrorKind::InvalidExit => "invalidexit",
+            ErrorKind::InvalidFileAccess => "invalidfileaccess",
+            ErrorKind::InvalidRestore => "invalidrestore",
+            ErrorKind::IoError => "ioerror",
+            ErrorKind::LimitCheck => "limitcheck",
+            ErrorKind::RangeCheck => "rangecheck",
+            ErrorKind::StackOverflow => "stackoverflow",
+            ErrorKind::StackUnderflow => "stackunderflow",
+            ErrorKind::SyntaxError => "syntaxerror",
+            ErrorKind::TypeCheck => "typecheck",
+            ErrorKind::Undefined => "undefined",
+            ErrorKind::UndefinedFileName => "undefinedfilename",
+            ErrorKind::UndefinedResult => "undefinedresult",
+            ErrorKind::UnmatchedMark => "unmatchedmark",
+            ErrorKind::Unregistered => "unregistered",
+            ErrorKind::VmError => "VMerror",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A PostScript error: its name, the object being executed when it was
+/// raised and the operands the failing command had popped (bottom first).
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub command: Option<Object>,
+    pub operands: Vec<Object>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            command: None,
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn with_operands(kind: ErrorKind, operands: Vec<Object>) -> Self {
+        Self {
+            kind,
+            command: None,
+            operands,
+        }
+    }
+
+    /// Record the offending command, unless a more specific one is already set.
+    pub fn in_command(mut self, command: Object) -> Self {
+        if self.command.is_none() {
+            self.command = Some(command);
+        }
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/{}", self.kind)?;
+        if let Some(command) = &self.command {
+            write!(f, " in {}", describe_command(command))?;
+        }
+        if !self.operands.is_empty() {
+            write!(f, ", operands:")?;
+            for operand in self.operands.iter() {
+                write!(f, " {operand}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// How a command is named in error reports.
+pub(crate) fn describe_command(command: &Object) -> String {
+    match command {
+        Object::Operator(_, op) => op.to_string(),
+        Object::Name(_, name) => name.to_string(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,154 @@
+use crate::Error;
+use crate::ErrorKind::*;
+use crate::Position;
+use crate::Token;
+use crate::TokenReader;
+use log::debug;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+enum Stream {
+    Input(TokenReader<Box<dyn BufRead>>),
+    Output(Box<dyn Write>),
+    Closed,
+}
+
+/// A PostScript file: a reference to an input or an output stream. As for
+/// dictionaries, the stream is shared by all references, so that reading or
+/// closing it through one is seen through all.
+#[derive(Clone)]
+pub struct PsFile {
+    name: Rc<str>,
+    stream: Rc<RefCell<Stream>>,
+}
+
+fn write_error(name: &str, e: io::Error) -> Error {
+    debug!("error on writing to {name}: {e}");
+    Error::new(IoError)
+}
+
+impl PsFile {
+    pub fn reader(name: &str, reader: Box<dyn BufRead>) -> Self {
+        Self::with_stream(name, Stream::Input(TokenReader::new(name, reader)))
+    }
+
+    pub fn writer(name: &str, writer: Box<dyn Write>) -> Self {
+        Self::with_stream(name, Stream::Output(writer))
+    }
+
+    /// A file which is already closed.
+    pub fn closed(name: &str) -> Self {
+        Self::with_stream(name, Stream::Closed)
+    }
+
+    fn with_stream(name: &str, stream: Stream) -> Self {
+        Self {
+            name: name.into(),
+            stream: Rc::new(RefCell::new(stream)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_input(&self) -> bool {
+        matches!(*self.stream.borrow(), Stream::Input(_))
+    }
+
+    pub fn is_output(&self) -> bool {
+        matches!(*self.stream.borrow(), Stream::Output(_))
+    }
+
+    /// Read the file through an input stream. A closed file reads as empty;
+    /// reading an output file is an ioerror.
+    fn with_input<T>(
+        &self,
+        read: impl FnOnce(&mut TokenReader<Box<dyn BufRead>>) -> Result<T, Error>,
+        closed: T,
+    ) -> Result<T, Error> {
+        match &mut *self.stream.borrow_mut() {
+            Stream::Input(reader) => read(reader),
+            Stream::Closed => Ok(closed),
+            Stream::Output(_) => Err(Error::new(IoError)),
+        }
+    }
+
+    /// The next token, see `TokenReader::next_token`.
+    pub fn next_token(&self) -> Option<Result<Token, Error>> {
+        match &mut *self.stream.borrow_mut() {
+            Stream::Input(reader) => reader.next_token(),
+            Stream::Closed => None,
+            Stream::Output(_) => Some(Err(Error::new(IoError))),
+        }
+    }
+
+    /// The position of the last token read, for input files.
+    pub fn position(&self) -> Option<Position> {
+        match &*self.stream.borrow() {
+            Stream::Input(reader) => Some(reader.position().clone()),
+            _ => None,
+        }
+    }
+
+    pub fn read_byte(&self) -> Result<Option<u8>, Error> {
+        self.with_input(|reader| reader.read_byte(), None)
+    }
+
+    pub fn read_line(&self) -> Result<(Vec<u8>, bool), Error> {
+        self.with_input(|reader| reader.read_line(), (Vec::new(), false))
+    }
+
+    pub fn read_bytes(&self, count: usize) -> Result<Vec<u8>, Error> {
+        self.with_input(|reader| reader.read_bytes(count), Vec::new())
+    }
+
+    /// The number of bytes which can be read without waiting, `None` at the
+    /// end of input and for output files.
+    pub fn bytes_available(&self) -> Option<usize> {
+        match &mut *self.stream.borrow_mut() {
+            Stream::Input(reader) => reader.bytes_available(),
+            _ => None,
+        }
+    }
+
+    pub fn write(&self, bytes: &[u8]) -> Result<(), Error> {
+        match &mut *self.stream.borrow_mut() {
+            Stream::Output(writer) => writer
+                .write_all(bytes)
+                .map_err(|e| write_error(&self.name, e)),
+            _ => Err(Error::new(IoError)),
+        }
+    }
+
+    /// Write out buffered output, or discard the rest of the input.
+    pub fn flush(&self) -> Result<(), Error> {
+        match &mut *self.stream.borrow_mut() {
+            Stream::Input(reader) => reader.skip_to_end(),
+            Stream::Output(writer) => writer.flush().map_err(|e| write_error(&self.name, e)),
+            Stream::Closed => Ok(()),
+        }
+    }
+
+    pub fn close(&self) -> Result<(), Error> {
+        let result = match &mut *self.stream.borrow_mut() {
+            Stream::Output(writer) => writer.flush().map_err(|e| write_error(&self.name, e)),
+            _ => Ok(()),
+        };
+        *self.stream.borrow_mut() = Stream::Closed;
+        result
+    }
+
+    /// Whether both reference the same stream.
+    pub fn ptr_eq(&self, other: &PsFile) -> bool {
+        Rc::ptr_eq(&self.stream, &other.stream)
+    }
+}
+
+impl Debug for PsFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "File({})", self.name)
+    }
+}
@@ -1,15 +1,31 @@
+mod array;
+mod bytecode;
+mod dict;
 mod dstack;
 mod engine;
+mod error;
+mod file;
+mod name;
 mod object;
 mod proc_builder;
+mod reader;
 mod scanner;
+mod string;
 mod token;
+mod vm;
 mod xstack;
 
+pub use array::PsArray;
+pub use dict::{Dict, Key};
 pub use dstack::DictStack;
 pub use engine::Engine;
-pub use object::{Object, ObjectMode, Operator};
+pub use error::{Error, ErrorKind};
+pub use file::PsFile;
+pub use name::Name;
+pub use object::{Access, Object, ObjectMode, Operator};
 pub use proc_builder::ProcBuilder;
+pub use reader::{Position, TokenReader};
 pub use scanner::Scanner;
-pub use token::Token;
-pub use xstack::{ExecStack, OnceRunner};
+pub use string::PsString;
+pub use token::{Number, Token};
+pub use xstack::ExecStack;
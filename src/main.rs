@@ -1,9 +1,10 @@
 use csgps::Scanner;
 use std::env;
+use std::process::ExitCode;
 
 use log::debug;
 
-fn main() {
+fn main() -> ExitCode {
     env_logger::init();
 
     let args: Vec<String> = env::args().collect();
@@ -17,9 +18,15 @@ fn main() {
             continue;
         }
 
-        if let Err(e) = scanner.execute_file(filename) {
-            println!("Error in {filename}: {e}");
-            return;
+        // errors are reported by errordict's handleerror, or on the standard
+        // error when it did not
+        let result = match filename.as_str() {
+            "-" => scanner.execute_stdin(),
+            _ => scanner.execute_file(filename),
+        };
+        if let Err(e) = result {
+            debug!("error in {filename}: {e}");
+            return ExitCode::FAILURE;
         }
     }
 
@@ -27,4 +34,5 @@ fn main() {
         scanner.enter_repl();
     }
     println!("bye.");
+    ExitCode::SUCCESS
 }
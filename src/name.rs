@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+
+/// A PostScript name, interned in the atom table: names with the same text
+/// are the same atom, so that they compare and hash as integers. The text is
+/// any sequence of bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Name(u32);
+
+/// The texts of the atoms, never freed, and the atom of each text.
+#[derive(Default)]
+struct Atoms {
+    texts: Vec<&'static [u8]>,
+    atoms: HashMap<&'static [u8], Name>,
+}
+
+thread_local! {
+    static ATOMS: RefCell<Atoms> = RefCell::default();
+}
+
+impl Name {
+    /// The atom of `text`, added to the table if not already there.
+    pub fn new(text: &[u8]) -> Self {
+        ATOMS.with_borrow_mut(|table| match table.atoms.get(text) {
+            Some(&name) => name,
+            None => {
+                let text: &'static [u8] = Box::leak(text.into());
+                let name = Name(table.texts.len() as u32);
+                table.texts.push(text);
+                table.atoms.insert(text, name);
+                name
+            }
+        })
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        ATOMS.with_borrow(|table| table.texts[self.0 as usize])
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_bytes().is_empty()
+    }
+}
+
+impl From<&str> for Name {
+    fn from(text: &str) -> Self {
+        Name::new(text.as_bytes())
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.as_bytes()))
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", String::from_utf8_lossy(self.as_bytes()))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{Dict, ErrorKind, Key, Name, PsArray, PsFile, PsString};
 use std::fmt::Display;
 
 #[derive(Debug, Clone)]
@@ -5,20 +6,49 @@ pub enum Object {
     Integer(i64),
     Real(f64),
     Bool(bool),
+    Null,
     Mark,
-    Array(ObjectMode, Vec<Object>),
-    Name(ObjectMode, String),
+    Array(ObjectMode, PsArray),
+    Dict(ObjectMode, Dict),
+    Name(ObjectMode, Name),
     Operator(ObjectMode, Operator),
-    String(ObjectMode, String),
-    File(ObjectMode, String),
+    String(ObjectMode, PsString),
+    File(ObjectMode, PsFile),
+    /// A snapshot of the virtual memory taken by `save`.
+    Save(u64),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ObjectMode {
     Literal,
     Executable,
 }
 
+/// Access attribute of composite objects, from the most to the least
+/// restrictive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Access {
+    NoAccess,
+    ExecuteOnly,
+    ReadOnly,
+    #[default]
+    Unlimited,
+}
+
+impl Access {
+    pub fn is_executable(self) -> bool {
+        self >= Access::ExecuteOnly
+    }
+
+    pub fn is_readable(self) -> bool {
+        self >= Access::ReadOnly
+    }
+
+    pub fn is_writable(self) -> bool {
+        self == Access::Unlimited
+    }
+}
+
 impl Display for ObjectMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -28,7 +58,7 @@ impl Display for ObjectMode {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Operator {
     Add,
     Load,
@@ -37,8 +67,32 @@ pub enum Operator {
     Index,
     Def,
     Div,
+    IDiv,
     Exec,
-    PopAndPrint, // ==
+    PopAndPrint,           // =
+    PopAndPrintOnly,       // =only
+    PopAndPrintSyntax,     // ==
+    PopAndPrintSyntaxOnly, // ==only
+    Print,
+    Flush,
+    File,
+    FlushFile,
+    Read,
+    Write,
+    ReadLine,
+    ReadString,
+    WriteString,
+    CloseFile,
+    BytesAvailable,
+    CurrentFile,
+    Run,
+    Token,
+    Save,
+    Restore,
+    SetGlobal,
+    CurrentGlobal,
+    Bind,
+    Stack,
     Dup,
     Eq,
     Ne,
@@ -56,22 +110,303 @@ pub enum Operator {
     Pstack,
     ClearToMark,
     CountToMark,
+    Begin,
+    End,
+    Dict,
+    EndDict, // >>
+    Known,
+    Where,
+    Undef,
+    CurrentDict,
+    CountDictStack,
+    DictStack,
+    Get,
+    Put,
+    HandleError,
+    Stop,
+    Stopped,
+    Exit,
+    For,
+    Loop,
+    Forall,
+    Neg,
+    Abs,
+    Lt,
+    Le,
+    Ge,
+    Sqrt,
+    Exp,
+    Ln,
+    Log,
+    Sin,
+    Cos,
+    Atan,
+    Ceiling,
+    Floor,
+    Round,
+    Truncate,
+    Cvi,
+    Cvr,
+    Cvn,
+    Cvs,
+    Cvrs,
+    Length,
+    Array,
+    ALoad,
+    AStore,
+    GetInterval,
+    PutInterval,
+    String,
+    Search,
+    AnchorSearch,
+    Type,
+    Cvx,
+    Cvlit,
+    XCheck,
+    RCheck,
+    WCheck,
+    ReadOnly,
+    ExecuteOnly,
+    NoAccess,
+    And,
+    Or,
+    Xor,
+    Not,
+    BitShift,
+    ErrorHandler(ErrorKind), // default errordict entries
+}
+
+impl Object {
+    /// The name returned by `type`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "integertype",
+            Object::Real(_) => "realtype",
+            Object::Bool(_) => "booleantype",
+            Object::Null => "nulltype",
+            Object::Mark => "marktype",
+            Object::Array(..) => "arraytype",
+            Object::Dict(..) => "dicttype",
+            Object::Name(..) => "nametype",
+            Object::Operator(..) => "operatortype",
+            Object::String(..) => "stringtype",
+            Object::File(..) => "filetype",
+            Object::Save(_) => "savetype",
+        }
+    }
+
+    pub fn mode(&self) -> Option<ObjectMode> {
+        match self {
+            Object::Array(m, _)
+            | Object::Dict(m, _)
+            | Object::Name(m, _)
+            | Object::Operator(m, _)
+            | Object::String(m, _)
+            | Object::File(m, _) => Some(*m),
+            _ => None,
+        }
+    }
+
+    /// The same object with another literal/executable attribute. Objects
+    /// without attribute are returned unchanged.
+    pub fn with_mode(self, mode: ObjectMode) -> Object {
+        match self {
+            Object::Array(_, a) => Object::Array(mode, a),
+            Object::Dict(_, d) => Object::Dict(mode, d),
+            Object::Name(_, n) => Object::Name(mode, n),
+            Object::Operator(_, o) => Object::Operator(mode, o),
+            Object::String(_, s) => Object::String(mode, s),
+            Object::File(_, f) => Object::File(mode, f),
+            other => other,
+        }
+    }
+
+    /// The access attribute of composite objects.
+    pub fn access(&self) -> Option<Access> {
+        match self {
+            Object::Array(_, a) => Some(a.access()),
+            Object::String(_, s) => Some(s.access()),
+            Object::Dict(_, d) => Some(d.access()),
+            _ => None,
+        }
+    }
+
+    /// The VM generation of composite objects, see `vm`.
+    pub fn birth(&self) -> Option<u64> {
+        match self {
+            Object::Array(_, a) => Some(a.birth()),
+            Object::String(_, s) => Some(s.birth()),
+            Object::Dict(_, d) => Some(d.birth()),
+            _ => None,
+        }
+    }
+
+    /// Whether the object is a composite object in local VM, which global
+    /// objects may not refer to.
+    pub fn is_local(&self) -> bool {
+        match self {
+            Object::Array(_, a) => !a.is_global(),
+            Object::String(_, s) => !s.is_global(),
+            Object::Dict(_, d) => !d.is_global(),
+            _ => false,
+        }
+    }
+
+    /// Whether the object is a composite object in global VM.
+    pub fn is_global(&self) -> bool {
+        self.birth().is_some() && !self.is_local()
+    }
+
+    /// Whether the object is not a composite one denying execute access.
+    pub fn is_executable(&self) -> bool {
+        self.access().is_none_or(Access::is_executable)
+    }
+
+    /// Whether the object is not a composite one denying read access.
+    pub fn is_readable(&self) -> bool {
+        self.access().is_none_or(Access::is_readable)
+    }
+
+    /// Whether the object is not a composite one denying write access.
+    pub fn is_writable(&self) -> bool {
+        self.access().is_none_or(Access::is_writable)
+    }
+
+    /// The text of an object as produced by `cvs`.
+    pub fn to_text(&self) -> Vec<u8> {
+        match self {
+            Object::Integer(i) => i.to_string().into_bytes(),
+            Object::Real(r) => format_real(*r).into_bytes(),
+            Object::Bool(b) => b.to_string().into_bytes(),
+            Object::String(_, s) => s.bytes(),
+            Object::Name(_, n) => n.as_bytes().to_vec(),
+            Object::Operator(_, op) => op.name().into_bytes(),
+            _ => b"--nostringval--".to_vec(),
+        }
+    }
+
+    /// The representation of an object as printed by `==`: PostScript
+    /// syntax where the object has one.
+    pub fn to_syntax(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_syntax(&mut bytes, &mut Vec::new());
+        bytes
+    }
+
+    /// Append the syntax of the object to `bytes`. `open` holds the arrays
+    /// being printed, within which the object is: an array containing itself
+    /// is printed as `-array-` where met again.
+    fn write_syntax(&self, bytes: &mut Vec<u8>, open: &mut Vec<PsArray>) {
+        match self {
+            _ if !self.is_readable() => {
+                let type_name = self.type_name().trim_end_matches("type");
+                bytes.extend(format!("-{type_name}-").into_bytes());
+            }
+            Object::Null => bytes.extend(b"null"),
+            Object::Mark => bytes.extend(b"-mark-"),
+            Object::Dict(..) => bytes.extend(b"-dict-"),
+            Object::File(..) => bytes.extend(b"-file-"),
+            Object::Save(_) => bytes.extend(b"-save-"),
+            Object::Operator(_, op) => bytes.extend(op.to_string().into_bytes()),
+            Object::Name(ObjectMode::Literal, n) => {
+                bytes.push(b'/');
+                bytes.extend(n.as_bytes());
+            }
+            Object::String(_, s) => {
+                bytes.push(b'(');
+                for byte in s.bytes() {
+                    match byte {
+                        b'(' | b')' | b'\\' => bytes.extend([b'\\', byte]),
+                        b'\n' => bytes.extend(b"\\n"),
+                        b'\r' => bytes.extend(b"\\r"),
+                        b'\t' => bytes.extend(b"\\t"),
+                        0x08 => bytes.extend(b"\\b"),
+                        0x0c => bytes.extend(b"\\f"),
+                        b' '..=b'~' => bytes.push(byte),
+                        _ => bytes.extend(format!("\\{byte:03o}").into_bytes()),
+                    }
+                }
+                bytes.push(b')');
+            }
+            Object::Array(_, a) if open.iter().any(|o| o.ptr_eq(a)) => {
+                bytes.extend(b"-array-");
+            }
+            Object::Array(m, a) => {
+                let (left, right) = match m {
+                    ObjectMode::Literal => (b'[', b']'),
+                    ObjectMode::Executable => (b'{', b'}'),
+                };
+                bytes.push(left);
+                open.push(a.clone());
+                for (i, object) in a.objects().iter().enumerate() {
+                    if i > 0 {
+                        bytes.push(b' ');
+                    }
+                    object.write_syntax(bytes, open);
+                }
+                open.pop();
+                bytes.push(right);
+            }
+            other => bytes.extend(other.to_text()),
+        }
+    }
+
+    /// The value of a number as a real, integers being promoted.
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Object::Integer(i) => Some(*i as f64),
+            Object::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// The dictionary key designated by the object, see `Key`.
+    pub fn as_key(&self) -> Option<Key> {
+        Key::new(self)
+    }
+
+    /// PostScript equality, as tested by `eq`: numbers are compared by value
+    /// whatever their type, strings and names by text, other objects by type
+    /// and value.
+    pub fn ps_eq(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::Integer(i1), Object::Integer(i2)) => i1 == i2,
+            (Object::Bool(b1), Object::Bool(b2)) => b1 == b2,
+            (Object::Null, Object::Null) | (Object::Mark, Object::Mark) => true,
+            (Object::Name(_, n1), Object::Name(_, n2)) => n1 == n2,
+            (Object::String(_, s1), Object::String(_, s2)) => s1.bytes() == s2.bytes(),
+            (Object::String(_, s), Object::Name(_, n))
+            | (Object::Name(_, n), Object::String(_, s)) => s.bytes() == n.as_bytes(),
+            (Object::Operator(_, o1), Object::Operator(_, o2)) => o1 == o2,
+            (Object::File(_, f1), Object::File(_, f2)) => f1.ptr_eq(f2),
+            (Object::Save(s1), Object::Save(s2)) => s1 == s2,
+            (Object::Array(_, a1), Object::Array(_, a2)) => a1.ptr_eq(a2),
+            (Object::Dict(_, d1), Object::Dict(_, d2)) => d1.ptr_eq(d2),
+            _ => match (self.as_real(), other.as_real()) {
+                (Some(r1), Some(r2)) => r1 == r2,
+                _ => false,
+            },
+        }
+    }
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Name(m, n) => write!(f, "{m}:Name({n})"),
-            Self::File(_, n) => write!(f, "File({n})"),
+            Self::File(_, file) => write!(f, "File({})", file.name()),
+            Self::Save(id) => write!(f, "Save({id})"),
             Self::Integer(i) => write!(f, "Integer({i})"),
             Self::Real(r) => write!(f, "Real({r})"),
             Self::Bool(b) => write!(f, "Bool({b})"),
+            Self::Null => write!(f, "Null"),
             Self::Mark => write!(f, "Mark"),
-            Self::String(_, s) => write!(f, "{s}"),
+            Self::Dict(m, _) => write!(f, "{m}:-dict-"),
+            Self::String(_, s) => write!(f, "{}", String::from_utf8_lossy(&s.bytes())),
             Self::Operator(m, s) => write!(f, "{m}:{s}"),
             Self::Array(m, a) => {
                 write!(f, "{m}:[")?;
-                for object in a.iter() {
+                for object in a.objects().iter() {
                     write!(f, "{object},")?;
                 }
                 write!(f, "]")
@@ -80,25 +415,90 @@ impl Display for Object {
     }
 }
 
+impl Operator {
+    /// The name the operator is known by in systemdict.
+    pub fn name(&self) -> String {
+        self.to_string().trim_matches('-').to_string()
+    }
+}
+
+/// Format a real the way PostScript does: 6 significant digits, in
+/// exponential notation for large and small magnitudes, always showing a
+/// fractional part so that it reads back as a real.
+pub fn format_real(r: f64) -> String {
+    if !r.is_finite() {
+        return r.to_string();
+    }
+    if r == 0.0 {
+        return "0.0".to_string();
+    }
+
+    let scientific = format!("{r:.5e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let trim = |digits: &str| {
+        if !digits.contains('.') {
+            return format!("{digits}.0");
+        }
+        let digits = digits.trim_end_matches('0');
+        match digits.strip_suffix('.') {
+            Some(integral) => format!("{integral}.0"),
+            None => digits.to_string(),
+        }
+    };
+
+    if (-4..6).contains(&exponent) {
+        let decimals = (5 - exponent) as usize;
+        trim(&format!("{r:.decimals$}"))
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{sign}{:02}", trim(mantissa), exponent.abs())
+    }
+}
+
 impl Display for Operator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Operator::Add => write!(f, "--add--"),
-            Operator::Load => write!(f, "--add--"),
+            Operator::Load => write!(f, "--load--"),
             Operator::Clear => write!(f, "--clear--"),
             Operator::Copy => write!(f, "--copy--"),
             Operator::Index => write!(f, "--index--"),
             Operator::Def => write!(f, "--def--"),
             Operator::Div => write!(f, "--div--"),
+            Operator::IDiv => write!(f, "--idiv--"),
             Operator::Exec => write!(f, "--exec--"),
             Operator::PopAndPrint => write!(f, "--=--"),
+            Operator::PopAndPrintOnly => write!(f, "--=only--"),
+            Operator::PopAndPrintSyntax => write!(f, "--==--"),
+            Operator::PopAndPrintSyntaxOnly => write!(f, "--==only--"),
+            Operator::Print => write!(f, "--print--"),
+            Operator::Flush => write!(f, "--flush--"),
+            Operator::File => write!(f, "--file--"),
+            Operator::FlushFile => write!(f, "--flushfile--"),
+            Operator::Read => write!(f, "--read--"),
+            Operator::Write => write!(f, "--write--"),
+            Operator::ReadLine => write!(f, "--readline--"),
+            Operator::ReadString => write!(f, "--readstring--"),
+            Operator::WriteString => write!(f, "--writestring--"),
+            Operator::CloseFile => write!(f, "--closefile--"),
+            Operator::BytesAvailable => write!(f, "--bytesavailable--"),
+            Operator::CurrentFile => write!(f, "--currentfile--"),
+            Operator::Run => write!(f, "--run--"),
+            Operator::Token => write!(f, "--token--"),
+            Operator::Save => write!(f, "--save--"),
+            Operator::Restore => write!(f, "--restore--"),
+            Operator::SetGlobal => write!(f, "--setglobal--"),
+            Operator::CurrentGlobal => write!(f, "--currentglobal--"),
+            Operator::Bind => write!(f, "--bind--"),
+            Operator::Stack => write!(f, "--stack--"),
             Operator::Dup => write!(f, "--dup--"),
             Operator::Eq => write!(f, "--eq--"),
             Operator::Ne => write!(f, "--ne--"),
             Operator::Exch => write!(f, "--exch--"),
             Operator::Gt => write!(f, "--gt--"),
             Operator::If => write!(f, "--if--"),
-            Operator::IfElse => write!(f, "--ifElse--"),
+            Operator::IfElse => write!(f, "--ifelse--"),
             Operator::Mod => write!(f, "--mod--"),
             Operator::Mul => write!(f, "--mul--"),
             Operator::Pop => write!(f, "--pop--"),
@@ -109,6 +509,70 @@ impl Display for Operator {
             Operator::Pstack => write!(f, "--pstack--"),
             Operator::ClearToMark => write!(f, "--cleartomark--"),
             Operator::CountToMark => write!(f, "--counttomark--"),
+            Operator::Begin => write!(f, "--begin--"),
+            Operator::End => write!(f, "--end--"),
+            Operator::Dict => write!(f, "--dict--"),
+            Operator::EndDict => write!(f, "-->>--"),
+            Operator::Known => write!(f, "--known--"),
+            Operator::Where => write!(f, "--where--"),
+            Operator::Undef => write!(f, "--undef--"),
+            Operator::CurrentDict => write!(f, "--currentdict--"),
+            Operator::CountDictStack => write!(f, "--countdictstack--"),
+            Operator::DictStack => write!(f, "--dictstack--"),
+            Operator::Get => write!(f, "--get--"),
+            Operator::Put => write!(f, "--put--"),
+            Operator::HandleError => write!(f, "--handleerror--"),
+            Operator::Stop => write!(f, "--stop--"),
+            Operator::Stopped => write!(f, "--stopped--"),
+            Operator::Exit => write!(f, "--exit--"),
+            Operator::For => write!(f, "--for--"),
+            Operator::Loop => write!(f, "--loop--"),
+            Operator::Forall => write!(f, "--forall--"),
+            Operator::Neg => write!(f, "--neg--"),
+            Operator::Abs => write!(f, "--abs--"),
+            Operator::Lt => write!(f, "--lt--"),
+            Operator::Le => write!(f, "--le--"),
+            Operator::Ge => write!(f, "--ge--"),
+            Operator::Sqrt => write!(f, "--sqrt--"),
+            Operator::Exp => write!(f, "--exp--"),
+            Operator::Ln => write!(f, "--ln--"),
+            Operator::Log => write!(f, "--log--"),
+            Operator::Sin => write!(f, "--sin--"),
+            Operator::Cos => write!(f, "--cos--"),
+            Operator::Atan => write!(f, "--atan--"),
+            Operator::Ceiling => write!(f, "--ceiling--"),
+            Operator::Floor => write!(f, "--floor--"),
+            Operator::Round => write!(f, "--round--"),
+            Operator::Truncate => write!(f, "--truncate--"),
+            Operator::Cvi => write!(f, "--cvi--"),
+            Operator::Cvr => write!(f, "--cvr--"),
+            Operator::Cvn => write!(f, "--cvn--"),
+            Operator::Cvs => write!(f, "--cvs--"),
+            Operator::Cvrs => write!(f, "--cvrs--"),
+            Operator::Length => write!(f, "--length--"),
+            Operator::Array => write!(f, "--array--"),
+            Operator::ALoad => write!(f, "--aload--"),
+            Operator::AStore => write!(f, "--astore--"),
+            Operator::GetInterval => write!(f, "--getinterval--"),
+            Operator::PutInterval => write!(f, "--putinterval--"),
+            Operator::String => write!(f, "--string--"),
+            Operator::Search => write!(f, "--search--"),
+            Operator::AnchorSearch => write!(f, "--anchorsearch--"),
+            Operator::Type => write!(f, "--type--"),
+            Operator::Cvx => write!(f, "--cvx--"),
+            Operator::Cvlit => write!(f, "--cvlit--"),
+            Operator::XCheck => write!(f, "--xcheck--"),
+            Operator::RCheck => write!(f, "--rcheck--"),
+            Operator::WCheck => write!(f, "--wcheck--"),
+            Operator::ReadOnly => write!(f, "--readonly--"),
+            Operator::ExecuteOnly => write!(f, "--executeonly--"),
+            Operator::NoAccess => write!(f, "--noaccess--"),
+            Operator::And => write!(f, "--and--"),
+            Operator::Or => write!(f, "--or--"),
+            Operator::Xor => write!(f, "--xor--"),
+            Operator::Not => write!(f, "--not--"),
+            Operator::BitShift => write!(f, "--bitshift--"),
+            Operator::ErrorHandler(kind) => write!(f, "--{kind}--"),
         }
     }
 }
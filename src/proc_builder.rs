@@ -1,5 +1,6 @@
 use crate::Object::{self, Array};
 use crate::ObjectMode;
+use crate::PsArray;
 
 #[derive(Default)]
 pub struct ProcBuilder {
@@ -21,7 +22,7 @@ impl ProcBuilder {
 
     pub fn close(&mut self) -> Option<Object> {
         if let Some(proc) = self.stack.pop() {
-            let object = Array(ObjectMode::Executable, proc);
+            let object = Array(ObjectMode::Executable, PsArray::new(proc));
 
             if self.stack.is_empty() {
                 Some(object)
@@ -0,0 +1,323 @@
+use crate::token::LexError;
+use crate::Error;
+use crate::ErrorKind::*;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::PsString;
+use crate::Token;
+use log::debug;
+use logos::Logos;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::rc::Rc;
+
+/// A location in a source of PostScript text. Lines and columns count from 1,
+/// columns in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(file: &str) -> Self {
+        Self {
+            file: file.into(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Move past `bytes`.
+    fn advance(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            match byte {
+                b'\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                _ => self.column += 1,
+            }
+        }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+fn is_white_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | 0x0c | 0)
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    is_white_space(byte) || b"()<>[]{}/%".contains(&byte)
+}
+
+/// The search for the end of a string running past the end of the buffer.
+/// It goes on over the input read next, so that the string is lexed again
+/// only once complete.
+struct Pending {
+    /// Index in the buffer of the next byte to look at.
+    scanned: usize,
+    closing: Closing,
+}
+
+enum Closing {
+    /// A `( ... )` string, at a nesting depth of parentheses, just after a
+    /// backslash or not.
+    Parenthesis { depth: usize, escape: bool },
+    /// A hexadecimal string, ending with `>`.
+    Hex,
+    /// A base-85 string, ending with `~>`, just after a `~` or not.
+    Ascii85 { tilde: bool },
+}
+
+impl Pending {
+    /// The search for the end of the string starting at `start` in `buffer`.
+    fn new(buffer: &[u8], start: usize) -> Self {
+        let (closing, opening) = match &buffer[start..] {
+            [b'(', ..] => {
+                let closing = Closing::Parenthesis {
+                    depth: 0,
+                    escape: false,
+                };
+                (closing, 1)
+            }
+            [b'<', b'~', ..] => (Closing::Ascii85 { tilde: false }, 2),
+            _ => (Closing::Hex, 1),
+        };
+        Self {
+            scanned: start + opening,
+            closing,
+        }
+    }
+
+    /// Look at the bytes of `buffer` not seen yet. Returns whether the string
+    /// may end among them.
+    fn resume(&mut self, buffer: &[u8]) -> bool {
+        while let Some(&byte) = buffer.get(self.scanned) {
+            self.scanned += 1;
+            match &mut self.closing {
+                Closing::Parenthesis { escape, .. } if *escape => *escape = false,
+                Closing::Parenthesis { depth, escape } => match byte {
+                    b'\\' => *escape = true,
+                    b'(' => *depth += 1,
+                    b')' if *depth == 0 => return true,
+                    b')' => *depth -= 1,
+                    _ => (),
+                },
+                Closing::Hex if byte == b'>' => return true,
+                Closing::Hex => (),
+                Closing::Ascii85 { tilde } if *tilde && byte == b'>' => return true,
+                Closing::Ascii85 { tilde } => *tilde = byte == b'~',
+            }
+        }
+        false
+    }
+}
+
+/// A buffered input source read either by tokens or by bytes. Input is read a
+/// line at a time, and only as long as a token may be incomplete, so that
+/// what follows a token is left to the byte reads.
+pub struct TokenReader<R> {
+    reader: R,
+    /// Input read, consumed up to `offset`. Consumed input is dropped when
+    /// more is read, once it makes up half of the buffer.
+    buffer: Vec<u8>,
+    offset: usize,
+    /// Position of the input at `offset`.
+    start: Position,
+    /// Position of the last token read.
+    position: Position,
+    /// The string the next token starts with, if found to be incomplete.
+    pending: Option<Pending>,
+    eof: bool,
+}
+
+impl<R: BufRead> TokenReader<R> {
+    pub fn new(file: &str, reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            offset: 0,
+            start: Position::new(file),
+            position: Position::new(file),
+            pending: None,
+            eof: false,
+        }
+    }
+
+    /// The position of the last token read.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// The next token, or `None` at the end of input. Tokens which cannot be
+    /// lexed give a syntaxerror.
+    pub fn next_token(&mut self) -> Option<Result<Token, Error>> {
+        loop {
+            if let Some(pending) = &mut self.pending {
+                if !pending.resume(&self.buffer) && !self.eof {
+                    if let Err(error) = self.refill() {
+                        return Some(Err(error));
+                    }
+                    continue;
+                }
+                self.pending = None;
+            }
+
+            let input = self.unread();
+            let mut lex = Token::lexer(input);
+            let (token, span, len) = (lex.next(), lex.span(), input.len());
+
+            let Some(token) = token else {
+                // nothing but white space and comments
+                self.consume(len);
+                if self.eof {
+                    return None;
+                }
+                if let Err(error) = self.refill() {
+                    return Some(Err(error));
+                }
+                continue;
+            };
+
+            // a token reaching the end of the buffer may go on in more input
+            let incomplete = match &token {
+                Err(LexError::Unterminated) => {
+                    self.pending = Some(Pending::new(&self.buffer, self.offset + span.start));
+                    true
+                }
+                _ => span.end == len,
+            };
+            if incomplete && !self.eof {
+                if let Err(error) = self.refill() {
+                    return Some(Err(error));
+                }
+                continue;
+            }
+            self.pending = None;
+
+            let input = &self.buffer[self.offset..];
+            let token = token.map_err(|_| {
+                let text = PsString::new(input[span.clone()].to_vec());
+                let text = Object::String(Literal, text);
+                Error::new(SyntaxError).in_command(text)
+            });
+            self.position = self.start.clone();
+            self.position.advance(&input[..span.start]);
+
+            // the white space ending a token is part of it
+            let mut end = span.end;
+            let last = input[end - 1];
+            if !is_delimiter(last) && input.get(end).is_some_and(|&b| is_white_space(b)) {
+                end += 1;
+            }
+            self.consume(end);
+            return Some(token);
+        }
+    }
+
+    /// Read more input for an incomplete token, which is given up on error.
+    fn refill(&mut self) -> Result<(), Error> {
+        self.fill().inspect_err(|_| self.pending = None)
+    }
+
+    /// The input read but not consumed yet.
+    fn unread(&self) -> &[u8] {
+        &self.buffer[self.offset..]
+    }
+
+    /// The next byte, or `None` at the end of input.
+    pub fn read_byte(&mut self) -> Result<Option<u8>, Error> {
+        if self.unread().is_empty() {
+            self.fill()?;
+        }
+        let byte = self.unread().first().copied();
+        if byte.is_some() {
+            self.consume(1);
+        }
+        Ok(byte)
+    }
+
+    /// The bytes up to the next newline, which is consumed but not returned,
+    /// and whether there was one before the end of input.
+    pub fn read_line(&mut self) -> Result<(Vec<u8>, bool), Error> {
+        loop {
+            if let Some(end) = self.unread().iter().position(|&b| b == b'\n') {
+                let line = self.unread()[..end].to_vec();
+                self.consume(end + 1);
+                return Ok((line, true));
+            }
+            if self.eof {
+                let line = self.unread().to_vec();
+                self.consume(line.len());
+                return Ok((line, false));
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Up to `count` bytes, less only at the end of input.
+    pub fn read_bytes(&mut self, count: usize) -> Result<Vec<u8>, Error> {
+        while self.unread().len() < count && !self.eof {
+            self.fill()?;
+        }
+        let unread = self.unread();
+        let bytes = unread[..count.min(unread.len())].to_vec();
+        self.consume(bytes.len());
+        Ok(bytes)
+    }
+
+    /// The number of bytes which can be read without waiting, `None` at the
+    /// end of input.
+    pub fn bytes_available(&mut self) -> Option<usize> {
+        let available = match self.unread().len() {
+            0 if self.eof => 0,
+            0 => self.reader.fill_buf().map_or(0, |bytes| bytes.len()),
+            len => len,
+        };
+        (available > 0).then_some(available)
+    }
+
+    /// Discard the rest of the input.
+    pub fn skip_to_end(&mut self) -> Result<(), Error> {
+        while !self.eof {
+            self.consume(self.unread().len());
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    /// Read one more line of input.
+    fn fill(&mut self) -> Result<(), Error> {
+        if self.offset > 0 && self.offset >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.offset);
+            if let Some(pending) = &mut self.pending {
+                pending.scanned -= self.offset;
+            }
+            self.offset = 0;
+        }
+        match self.reader.read_until(b'\n', &mut self.buffer) {
+            Ok(0) => self.eof = true,
+            Ok(_) => (),
+            Err(e) => {
+                debug!("error on reading {}: {e}", self.start.file);
+                self.eof = true;
+                return Err(Error::new(IoError));
+            }
+        }
+        Ok(())
+    }
+
+    /// Consume the next `len` bytes of the buffer.
+    fn consume(&mut self, len: usize) {
+        self.start
+            .advance(&self.buffer[self.offset..self.offset + len]);
+        self.offset += len;
+    }
+}
@@ -1,23 +1,39 @@
+use crate::token::LexError;
 use crate::Engine;
-use crate::ProcBuilder;
+use crate::Error;
+use crate::ErrorKind::*;
+use crate::Object;
+use crate::ObjectMode::*;
+use crate::PsFile;
 use crate::Token;
 use log::debug;
 use logos::Logos;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{BufReader, Cursor, Read};
 
 pub struct Scanner {
-    proc_builder: ProcBuilder,
     engine: Engine,
 }
 
 impl Default for Scanner {
     fn default() -> Self {
-        Self {
-            proc_builder: ProcBuilder::new(),
-            engine: Engine::new(),
+        Scanner::with_engine(Engine::new())
+    }
+}
+
+/// Whether `text` is a whole statement: no procedure or string is left open
+/// at its end.
+fn is_complete(text: &str) -> bool {
+    let mut depth = 0;
+    for token in Token::lexer(text.as_bytes()) {
+        match token {
+            Ok(Token::BeginProc) => depth += 1,
+            Ok(Token::EndProc) => depth -= 1,
+            Err(LexError::Unterminated) => return false,
+            _ => (),
         }
     }
+    depth <= 0
 }
 
 impl Scanner {
@@ -25,77 +41,76 @@ impl Scanner {
         Scanner::default()
     }
 
-    pub fn execute_string(&mut self, contents: &str) -> Result<(), String> {
-        let mut lex = Token::lexer(contents);
+    /// A scanner feeding the given engine, e.g. one built with
+    /// `Engine::with_output`.
+    pub fn with_engine(engine: Engine) -> Self {
+        Self { engine }
+    }
 
-        loop {
-            self.engine.process_execution_stack()?;
+    pub fn execute_string(&mut self, contents: &str) -> Result<(), Error> {
+        self.execute_reader("%string", Cursor::new(contents.as_bytes().to_vec()))
+    }
 
-            match lex.next() {
-                Some(Ok(Token::BeginProc)) => self.proc_builder.open(),
-                Some(Ok(Token::EndProc)) => {
-                    if !self.proc_builder.is_open() {
-                        return Err("syntax error".to_string());
-                    }
-                    if let Some(proc) = self.proc_builder.close() {
-                        debug!("build proc");
-                        self.engine.push(proc);
-                    }
-                }
-                Some(Ok(Token::ImmName(name))) => {
-                    let response = self.engine.get_object_by_name(&name);
-                    match response {
-                        Some(object) => {
-                            if self.proc_builder.is_open() {
-                                self.proc_builder.push(object);
-                            } else {
-                                self.engine.push(object);
-                            }
-                        }
-                        None => {
-                            todo!("undefined error")
-                        }
-                    }
-                }
-                Some(Ok(token)) => {
-                    let object = token.to_object();
-                    if self.proc_builder.is_open() {
-                        self.proc_builder.push(object);
-                    } else {
-                        self.engine.process_object(object)?;
-                    }
-                }
-                Some(Err(_)) => return Err(format!("parse error: {}", lex.slice())),
-                None => {
-                    return Ok(());
-                }
-            };
-        }
+    /// Execute the PostScript program read from `reader`, as it is read.
+    /// `file` names the source in error reports.
+    pub fn execute_reader<R: Read + 'static>(
+        &mut self,
+        file: &str,
+        reader: R,
+    ) -> Result<(), Error> {
+        let file = PsFile::reader(file, Box::new(BufReader::new(reader)));
+        self.engine.execute_file(file)
+    }
+
+    /// Execute the program read from `%stdin`, the file `%stdin` operands
+    /// read from too.
+    pub fn execute_stdin(&mut self) -> Result<(), Error> {
+        let stdin = self.engine.stdin();
+        self.engine.execute_file(stdin)
     }
 
-    pub fn execute_file(&mut self, filename: &str) -> Result<(), String> {
-        let mut file = match File::open(filename) {
-            Err(e) => return Err(format!("error on opening {filename}: {e}")),
+    pub fn execute_file(&mut self, filename: &str) -> Result<(), Error> {
+        let command = Object::String(Literal, filename.into());
+        let file = match File::open(filename) {
+            Err(e) => {
+                debug!("error on opening {filename}: {e}");
+                return self
+                    .engine
+                    .raise(Error::new(UndefinedFileName).in_command(command));
+            }
             Ok(file) => file,
         };
 
-        let mut contents = String::new();
-        if let Err(e) = file.read_to_string(&mut contents) {
-            return Err(format!("error on loading {filename}: {e}"));
-        }
-
         debug!("execute_file {filename} ");
-        self.execute_string(&contents)
+        self.execute_reader(filename, file)
     }
 
+    /// Read statements from the terminal and execute them. Lines are gathered
+    /// until procedures and strings opened on them are closed.
     pub fn enter_repl(&mut self) {
         let mut rl = rustyline::DefaultEditor::new().unwrap();
+        let mut statement = String::new();
         loop {
-            let readline = rl.readline(&format!("csg-PS [{}] > ", self.engine.get_stack_size()));
-            match readline {
+            let prompt = match statement.is_empty() {
+                true => format!("csg-PS [{}] > ", self.engine.get_stack_size()),
+                false => "... ".to_string(),
+            };
+            match rl.readline(&prompt) {
                 Ok(line) => {
-                    if let Err(e) = self.execute_string(&line) {
-                        println!("Error : {e}");
+                    statement.push_str(&line);
+                    statement.push('\n');
+                    if !is_complete(&statement) {
+                        continue;
+                    }
+
+                    // errors are reported by errordict's handleerror
+                    let contents = std::mem::take(&mut statement);
+                    let reader = Cursor::new(contents.into_bytes());
+                    if let Err(e) = self.execute_reader("%statementedit", reader) {
+                        debug!("error: {e}");
+                    }
+                    if let Err(e) = self.engine.flush() {
+                        debug!("error: {e}");
                     }
                 }
                 Err(_) => break,
@@ -0,0 +1,124 @@
+use crate::vm;
+use crate::Access;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// A PostScript string: a reference to an interval of a shared byte buffer.
+/// Cloning a `PsString`, or taking an interval of it, never copies the bytes
+/// so that modifications are seen through every reference.
+#[derive(Clone)]
+pub struct PsString {
+    data: Rc<RefCell<Vec<u8>>>,
+    start: usize,
+    len: usize,
+    access: Access,
+    birth: u64,
+    global: bool,
+}
+
+impl PsString {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let (birth, global) = vm::allocation();
+        Self {
+            data: Rc::new(RefCell::new(bytes)),
+            start: 0,
+            len,
+            access: Access::Unlimited,
+            birth,
+            global,
+        }
+    }
+
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    /// The same string with another access attribute.
+    pub fn with_access(&self, access: Access) -> Self {
+        Self {
+            access,
+            ..self.clone()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<u8> {
+        match index < self.len {
+            true => Some(self.data.borrow()[self.start + index]),
+            false => None,
+        }
+    }
+
+    pub fn put(&self, index: usize, byte: u8) -> Option<()> {
+        match index < self.len {
+            true => {
+                self.data.borrow_mut()[self.start + index] = byte;
+                Some(())
+            }
+            false => None,
+        }
+    }
+
+    /// The VM generation the string was created in.
+    pub fn birth(&self) -> u64 {
+        self.birth
+    }
+
+    /// Whether the string was allocated in global VM.
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    /// A copy of the bytes.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.data.borrow()[self.start..self.start + self.len].to_vec()
+    }
+
+    /// The `count` bytes starting at `index`, sharing this string's storage.
+    pub fn interval(&self, index: usize, count: usize) -> Option<PsString> {
+        match index.checked_add(count) {
+            Some(end) if end <= self.len => Some(Self {
+                data: self.data.clone(),
+                start: self.start + index,
+                len: count,
+                access: self.access,
+                birth: self.birth,
+                global: self.global,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Overwrite the bytes starting at `index` with `bytes`.
+    pub fn put_interval(&self, index: usize, bytes: &[u8]) -> Option<()> {
+        match index.checked_add(bytes.len()) {
+            Some(end) if end <= self.len => {
+                let start = self.start + index;
+                self.data.borrow_mut()[start..start + bytes.len()].copy_from_slice(bytes);
+                Some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for PsString {
+    fn from(s: &str) -> Self {
+        PsString::new(s.as_bytes().to_vec())
+    }
+}
+
+impl Debug for PsString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", String::from_utf8_lossy(&self.bytes()))
+    }
+}
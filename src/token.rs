@@ -1,49 +1,260 @@
+use crate::Name;
 use crate::Object;
 use crate::ObjectMode::*;
-use logos::Logos;
+use crate::PsString;
+use logos::{Lexer, Logos};
+
+/// Why the lexer failed to read a token.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LexError {
+    #[default]
+    Invalid,
+    /// The input ends within a string, which more input may complete.
+    Unterminated,
+}
+
+/// A number token. Integers too large for an `Integer` are reals.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Number {
+    Integer(i64),
+    Real(f64),
+}
 
 #[derive(Logos, Debug, PartialEq, Clone)]
-#[logos(skip r"[ \t\n\f]+")]
-#[logos(skip r"%.*")]
+#[logos(source = [u8])]
+#[logos(error = LexError)]
+#[logos(skip r"[ \t\r\n\f\x00]+")]
+#[logos(skip br"%[^\n]*")]
 pub enum Token {
-    #[regex(r"[-+]?[0-9]+\.[0-9]+", |lex| lex.slice().parse().ok())]
-    #[regex(r"[-+]?[0-9]+\.", |lex| lex.slice().parse().ok())]
-    #[regex(r"[-+]?\.[0-9]+", |lex| lex.slice().parse().ok())]
-    Real(f64),
-    #[regex(r"[-+]?[0-9]+", |lex| lex.slice().parse().ok())]
-    Integer(i64),
-    #[regex(r"true|false", |lex| lex.slice() == "true")]
+    #[regex(r"[-+]?([0-9]+\.[0-9]*|\.[0-9]+)([eE][-+]?[0-9]+)?", real)]
+    #[regex(r"[-+]?[0-9]+[eE][-+]?[0-9]+", real)]
+    #[regex(r"[-+]?[0-9]+", decimal_integer, priority = 2)]
+    // the digits must be valid in the base, or the token is a name
+    #[regex(
+        "2#[0-1]+|3#[0-2]+|4#[0-3]+|5#[0-4]+|6#[0-5]+|7#[0-6]+|8#[0-7]+|9#[0-8]+|\
+         10#[0-9]+|11#[0-9aA]+|12#[0-9a-bA-B]+|13#[0-9a-cA-C]+|14#[0-9a-dA-D]+|\
+         15#[0-9a-eA-E]+|16#[0-9a-fA-F]+|17#[0-9a-gA-G]+|18#[0-9a-hA-H]+|\
+         19#[0-9a-iA-I]+|20#[0-9a-jA-J]+|21#[0-9a-kA-K]+|22#[0-9a-lA-L]+|\
+         23#[0-9a-mA-M]+|24#[0-9a-nA-N]+|25#[0-9a-oA-O]+|26#[0-9a-pA-P]+|\
+         27#[0-9a-qA-Q]+|28#[0-9a-rA-R]+|29#[0-9a-sA-S]+|30#[0-9a-tA-T]+|\
+         31#[0-9a-uA-U]+|32#[0-9a-vA-V]+|33#[0-9a-wA-W]+|34#[0-9a-xA-X]+|\
+         35#[0-9a-yA-Y]+|36#[0-9a-zA-Z]+",
+        radix_integer
+    )]
+    Number(Number),
+    #[regex(r"true|false", |lex| lex.slice() == b"true")]
     Bool(bool),
     #[regex(r"\[|<<")]
     #[token(r"mark")]
     Mark,
-    #[token(r">>")]
-    Dict,
     #[token(r"{")]
     BeginProc,
     #[token(r"}")]
     EndProc,
-    #[regex(r"//[a-zA-Z][a-zA-Z0-9_-]*", |lex| lex.slice()[2..].to_owned())]
-    ImmName(String),
-    #[regex(r"/[a-zA-Z][a-zA-Z0-9_-]*", |lex| lex.slice()[1..].to_owned())]
-    LitName(String),
-    #[regex(r"[a-zA-Z][a-zA-Z0-9_-]*", |lex| lex.slice().to_owned())]
-    #[token(r"=", |lex| lex.slice().to_owned())]
-    #[token(r"==", |lex| lex.slice().to_owned())]
-    #[regex(r"\]|>>", |lex| lex.slice().to_owned())]
-    ExeName(String),
+    #[token(r"(", literal_string)]
+    #[token(r"<", hex_string)]
+    #[token(r"<~", ascii85_string)]
+    String(Vec<u8>),
+    // names are runs of regular characters, i.e. any byte but white space
+    // and the delimiters ( ) < > [ ] { } / %; a token which is also a
+    // number is a number
+    #[regex(br"//[^ \t\r\n\f\x00()<>\[\]{}/%]*", |lex| lex.slice()[2..].to_vec())]
+    ImmName(Vec<u8>),
+    #[regex(br"/[^ \t\r\n\f\x00()<>\[\]{}/%]*", |lex| lex.slice()[1..].to_vec())]
+    LitName(Vec<u8>),
+    #[regex(br"[^ \t\r\n\f\x00()<>\[\]{}/%]+", |lex| lex.slice().to_vec(), priority = 1)]
+    #[regex(r"\]|>>", |lex| lex.slice().to_vec())]
+    ExeName(Vec<u8>),
 }
 
 impl Token {
     pub fn to_object(&self) -> Object {
         match self {
             Token::Bool(b) => Object::Bool(*b),
-            Token::Real(r) => Object::Real(*r),
-            Token::Integer(i) => Object::Integer(*i),
+            Token::Number(Number::Integer(i)) => Object::Integer(*i),
+            Token::Number(Number::Real(r)) => Object::Real(*r),
             Token::Mark => Object::Mark,
-            Token::ExeName(n) => Object::Name(Executable, n.clone()),
-            Token::LitName(n) => Object::Name(Literal, n.clone()),
+            Token::String(s) => Object::String(Literal, PsString::new(s.clone())),
+            Token::ExeName(n) => Object::Name(Executable, Name::new(n)),
+            Token::LitName(n) => Object::Name(Literal, Name::new(n)),
             _ => panic!("Token not expected {:?}", self),
         }
     }
 }
+
+/// Parse a whole string as a number, with the syntax of the lexer. Leading
+/// and trailing white space is allowed.
+pub fn parse_number(s: &[u8]) -> Option<Object> {
+    let mut lex = Token::lexer(s);
+    match (lex.next(), lex.next()) {
+        (Some(Ok(token @ Token::Number(_))), None) => Some(token.to_object()),
+        _ => None,
+    }
+}
+
+/// The text of the token, when only made of ASCII characters as numbers are.
+fn ascii<'s>(lex: &Lexer<'s, Token>) -> &'s str {
+    std::str::from_utf8(lex.slice()).unwrap()
+}
+
+fn real(lex: &mut Lexer<Token>) -> Option<Number> {
+    let r: f64 = ascii(lex).parse().ok()?;
+    r.is_finite().then_some(Number::Real(r))
+}
+
+/// A decimal integer, or a real if it is too large.
+fn decimal_integer(lex: &mut Lexer<Token>) -> Option<Number> {
+    match ascii(lex).parse() {
+        Ok(i) => Some(Number::Integer(i)),
+        Err(_) => real(lex),
+    }
+}
+
+/// A `base#digits` integer, `base` being from 2 to 36. The digits are an
+/// unsigned value which is taken in two's complement when it exceeds the
+/// range of positive integers; larger values are invalid.
+fn radix_integer(lex: &mut Lexer<Token>) -> Option<Number> {
+    let (base, digits) = ascii(lex).split_once('#')?;
+    let value = u64::from_str_radix(digits, base.parse().ok()?).ok()?;
+    Some(Number::Integer(value as i64))
+}
+
+/// Lex a `( ... )` string, the opening parenthesis being already consumed.
+/// Balanced parentheses need no escaping; end-of-line sequences and
+/// backslash escapes are translated.
+fn literal_string(lex: &mut Lexer<Token>) -> Result<Vec<u8>, LexError> {
+    let remainder = lex.remainder();
+    let mut bytes = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+
+    loop {
+        let c = *remainder.get(i).ok_or(LexError::Unterminated)?;
+        i += 1;
+        match c {
+            b'(' => {
+                depth += 1;
+                bytes.push(c);
+            }
+            b')' if depth == 0 => {
+                lex.bump(i);
+                return Ok(bytes);
+            }
+            b')' => {
+                depth -= 1;
+                bytes.push(c);
+            }
+            b'\r' => {
+                if remainder.get(i) == Some(&b'\n') {
+                    i += 1;
+                }
+                bytes.push(b'\n');
+            }
+            b'\\' => {
+                let e = *remainder.get(i).ok_or(LexError::Unterminated)?;
+                i += 1;
+                match e {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(0x08),
+                    b'f' => bytes.push(0x0c),
+                    b'0'..=b'7' => {
+                        let mut code = (e - b'0') as u32;
+                        for _ in 0..2 {
+                            match remainder.get(i) {
+                                Some(d @ b'0'..=b'7') => {
+                                    code = code * 8 + (d - b'0') as u32;
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(code as u8);
+                    }
+                    // a backslash before an end-of-line continues the line
+                    b'\r' => {
+                        if remainder.get(i) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    b'\n' => (),
+                    // including \\, \( and \); otherwise the backslash is ignored
+                    other => bytes.push(other),
+                }
+            }
+            other => bytes.push(other),
+        }
+    }
+}
+
+/// Lex a `< ... >` hexadecimal string. White space is ignored and a
+/// missing final digit is taken as 0.
+fn hex_string(lex: &mut Lexer<Token>) -> Result<Vec<u8>, LexError> {
+    let remainder = lex.remainder();
+    let end = remainder
+        .iter()
+        .position(|&c| c == b'>')
+        .ok_or(LexError::Unterminated)?;
+    let mut digits = Vec::new();
+
+    for &c in &remainder[..end] {
+        match (c as char).to_digit(16) {
+            Some(d) => digits.push(d as u8),
+            None if c.is_ascii_whitespace() => (),
+            None => return Err(LexError::Invalid),
+        }
+    }
+    if digits.len() % 2 == 1 {
+        digits.push(0);
+    }
+
+    lex.bump(end + 1);
+    Ok(digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect())
+}
+
+/// Lex a `<~ ... ~>` ASCII base-85 string.
+fn ascii85_string(lex: &mut Lexer<Token>) -> Result<Vec<u8>, LexError> {
+    let remainder = lex.remainder();
+    let end = remainder
+        .windows(2)
+        .position(|w| w == b"~>")
+        .ok_or(LexError::Unterminated)?;
+    let mut bytes = Vec::new();
+    let mut group: Vec<u32> = Vec::with_capacity(5);
+
+    for &c in &remainder[..end] {
+        match c {
+            b'z' if group.is_empty() => bytes.extend([0; 4]),
+            b'!'..=b'u' => {
+                group.push((c - b'!') as u32);
+                if group.len() == 5 {
+                    bytes.extend(ascii85_group(&group).ok_or(LexError::Invalid)?);
+                    group.clear();
+                }
+            }
+            c if c.is_ascii_whitespace() => (),
+            _ => return Err(LexError::Invalid),
+        }
+    }
+
+    // a final partial group of n characters encodes n - 1 bytes
+    match group.len() {
+        0 => (),
+        1 => return Err(LexError::Invalid),
+        n => {
+            group.resize(5, (b'u' - b'!') as u32);
+            bytes.extend(&ascii85_group(&group).ok_or(LexError::Invalid)?[..n - 1]);
+        }
+    }
+
+    lex.bump(end + 2);
+    Ok(bytes)
+}
+
+fn ascii85_group(group: &[u32]) -> Option<[u8; 4]> {
+    let value = group.iter().try_fold(0u32, |value, digit| {
+        value.checked_mul(85)?.checked_add(*digit)
+    })?;
+    Some(value.to_be_bytes())
+}
@@ -0,0 +1,177 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+/// The virtual memory of an engine, as far as `save` and `restore` go.
+///
+/// Composite objects are allocated in local or global VM, depending on the
+/// allocation mode set by `setglobal`, and stamped with the generation they
+/// were created in; each `save` starts a new generation. The first
+/// modification of an older local array or dictionary after a save records
+/// how to undo it in the journal of that save, and `restore` replays the
+/// journals in reverse order. As in PostScript, string contents and global VM
+/// are not restored.
+///
+/// Each engine has its own VM, and objects belong to the VM of the engine
+/// running when they are created: see `Vm::enter`.
+#[derive(Clone, Default)]
+pub struct Vm(Rc<RefCell<State>>);
+
+#[derive(Default)]
+struct State {
+    generation: u64,
+    /// Whether composite objects are allocated in global VM.
+    global: bool,
+    saves: Vec<Save>,
+}
+
+struct Save {
+    id: u64,
+    /// The allocation mode when the save was taken.
+    global: bool,
+    journal: Vec<Box<dyn FnOnce()>>,
+}
+
+thread_local! {
+    /// The VM objects are allocated in.
+    static CURRENT: RefCell<Option<Vm>> = const { RefCell::new(None) };
+}
+
+/// The generation objects created now belong to and whether they are global:
+/// generation 0 in local VM outside of any VM.
+pub fn allocation() -> (u64, bool) {
+    CURRENT.with_borrow(|vm| {
+        vm.as_ref().map_or((0, false), |vm| {
+            let state = vm.0.borrow();
+            (state.generation, state.global)
+        })
+    })
+}
+
+/// Makes a VM the one objects are allocated in while it lives; the previous
+/// one is current again when it is dropped.
+pub struct Entered {
+    previous: Option<Vm>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT.set(self.previous.take());
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm::default()
+    }
+
+    /// Allocate objects in this VM until the returned guard is dropped.
+    pub fn enter(&self) -> Entered {
+        Entered {
+            previous: CURRENT.replace(Some(self.clone())),
+        }
+    }
+
+    /// Whether composite objects are allocated in global VM.
+    pub fn is_global(&self) -> bool {
+        self.0.borrow().global
+    }
+
+    pub fn set_global(&self, global: bool) {
+        self.0.borrow_mut().global = global;
+    }
+
+    /// Take a snapshot of the virtual memory, returning its identifier.
+    pub fn save(&self) -> u64 {
+        let mut state = self.0.borrow_mut();
+        state.generation += 1;
+        let id = state.generation;
+        let global = state.global;
+        state.saves.push(Save {
+            id,
+            global,
+            journal: Vec::new(),
+        });
+        id
+    }
+
+    /// Whether the snapshot `id` can still be restored.
+    pub fn is_valid(&self, id: u64) -> bool {
+        self.0.borrow().saves.iter().any(|save| save.id == id)
+    }
+
+    /// Undo every modification of local VM made since the snapshot `id`, and
+    /// reset the allocation mode, invalidating it and the snapshots taken
+    /// after it. Returns false if it was not valid.
+    pub fn restore(&self, id: u64) -> bool {
+        let saves = {
+            let mut state = self.0.borrow_mut();
+            let Some(index) = state.saves.iter().position(|save| save.id == id) else {
+                return false;
+            };
+            state.global = state.saves[index].global;
+            state.saves.split_off(index)
+        };
+        // the undo actions borrow the objects, not the VM
+        for save in saves.into_iter().rev() {
+            for undo in save.journal.into_iter().rev() {
+                undo();
+            }
+        }
+        true
+    }
+}
+
+/// The save-related state of an array or dictionary, shared by all its
+/// references.
+#[derive(Debug)]
+pub struct Stamp {
+    birth: u64,
+    global: bool,
+    /// The save the object was last recorded in the journal of.
+    journaled: Cell<u64>,
+    /// The VM the object was allocated in, if any.
+    vm: Weak<RefCell<State>>,
+}
+
+impl Default for Stamp {
+    fn default() -> Self {
+        let (birth, global) = allocation();
+        let vm = CURRENT.with_borrow(|vm| vm.as_ref().map(|vm| Rc::downgrade(&vm.0)));
+        Self {
+            birth,
+            global,
+            journaled: Cell::new(0),
+            vm: vm.unwrap_or_default(),
+        }
+    }
+}
+
+impl Stamp {
+    /// The generation the object was created in.
+    pub fn birth(&self) -> u64 {
+        self.birth
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    /// Called before modifying the object: if it is local, predates the
+    /// innermost save of its VM and was not recorded since, record the action
+    /// returned by `undo`, which restores the current contents.
+    pub fn record(&self, undo: impl FnOnce() -> Box<dyn FnOnce()>) {
+        if self.global {
+            return;
+        }
+        let Some(vm) = self.vm.upgrade() else {
+            return;
+        };
+        let mut state = vm.borrow_mut();
+        if let Some(save) = state.saves.last_mut() {
+            if self.birth < save.id && self.journaled.get() != save.id {
+                self.journaled.set(save.id);
+                save.journal.push(undo());
+            }
+        }
+    }
+}
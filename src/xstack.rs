@@ -1,76 +1,392 @@
+use crate::bytecode::Code;
+use crate::DictStack;
+use crate::Error;
+use crate::ErrorKind::*;
+use crate::Name;
 use crate::Object;
+use crate::ObjectMode::*;
+use crate::Position;
+use crate::ProcBuilder;
+use crate::PsArray;
+use crate::PsFile;
+use crate::PsString;
+use crate::Token;
+use log::debug;
+use std::io::Cursor;
 
+/// What a runner schedules next.
+pub enum Scheduled {
+    /// An object to execute, as met in a file.
+    Object(Object),
+    /// A procedure to run above the runner, which is asked again once it
+    /// completes.
+    Proc(PsArray),
+}
+
+/// A source of objects to execute other than a procedure: a file, a loop, or
+/// a context marker. Runners yield what to execute one step at a time;
+/// looping runners may push their control values on `operands` first.
+/// Runners reading source text look up immediately evaluated names in
+/// `dict_stack`, and may fail.
 pub trait ProcRunner {
-    fn get_object(&mut self) -> Option<Object>;
+    fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        dict_stack: &DictStack,
+    ) -> Option<Result<Scheduled, Error>>;
+
+    /// The file being executed, for runners reading one.
+    fn file(&self) -> Option<&PsFile> {
+        None
+    }
+
+    /// Whether `exit` terminates this runner.
+    fn is_loop(&self) -> bool {
+        false
+    }
+
+    /// Whether `stop` unwinds the execution stack down to this runner.
+    fn is_stopped_context(&self) -> bool {
+        false
+    }
+}
+
+pub struct RepeatRunner {
+    proc: PsArray,
+    times: i64,
+}
+
+impl RepeatRunner {
+    pub fn new(proc: PsArray, times: i64) -> Self {
+        Self { proc, times }
+    }
 }
 
-pub struct OnceRunner {
-    proc: Vec<Object>,
-    pc: usize,
+impl ProcRunner for RepeatRunner {
+    fn next_step(
+        &mut self,
+        _: &mut Vec<Object>,
+        _: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        if self.times == 0 {
+            return None;
+        }
+        self.times -= 1;
+        Some(Ok(Scheduled::Proc(self.proc.clone())))
+    }
+
+    fn is_loop(&self) -> bool {
+        true
+    }
 }
 
-impl OnceRunner {
-    pub fn new(proc: Vec<Object>) -> Self {
-        Self { proc, pc: 0 }
+pub struct LoopRunner {
+    proc: PsArray,
+}
+
+impl LoopRunner {
+    pub fn new(proc: PsArray) -> Self {
+        Self { proc }
     }
+}
 
-    pub fn reset(&mut self) {
-        self.pc = 0;
+impl ProcRunner for LoopRunner {
+    fn next_step(
+        &mut self,
+        _: &mut Vec<Object>,
+        _: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        Some(Ok(Scheduled::Proc(self.proc.clone())))
+    }
+
+    fn is_loop(&self) -> bool {
+        true
     }
 }
 
-impl ProcRunner for OnceRunner {
-    fn get_object(&mut self) -> Option<Object> {
-        let object;
+/// Control variable of a `for` loop: integer when both the initial value and
+/// the increment are integers, real otherwise. The integer value is `None`
+/// once it went past the bounds of integers, which ends the loop.
+pub enum ForControl {
+    Integer {
+        current: Option<i64>,
+        increment: i64,
+    },
+    Real {
+        current: f64,
+        increment: f64,
+    },
+}
 
-        if self.pc < self.proc.len() {
-            object = Some(self.proc[self.pc].clone());
-            self.pc += 1;
-        } else {
-            object = None;
+pub struct ForRunner {
+    proc: PsArray,
+    control: ForControl,
+    limit: f64,
+}
+
+impl ForRunner {
+    pub fn new(proc: PsArray, control: ForControl, limit: f64) -> Self {
+        Self {
+            proc,
+            control,
+            limit,
         }
+    }
 
-        object
+    fn next_control(&mut self) -> Option<Object> {
+        match &mut self.control {
+            ForControl::Integer { current, increment } => {
+                let value = (*current)?;
+                let done = match *increment >= 0 {
+                    true => value as f64 > self.limit,
+                    false => (value as f64) < self.limit,
+                };
+                if done {
+                    return None;
+                }
+                *current = value.checked_add(*increment);
+                Some(Object::Integer(value))
+            }
+            ForControl::Real { current, increment } => {
+                let value = *current;
+                let done = match *increment >= 0.0 {
+                    true => value > self.limit,
+                    false => value < self.limit,
+                };
+                if done {
+                    return None;
+                }
+                *current = value + *increment;
+                Some(Object::Real(value))
+            }
+        }
     }
 }
 
-pub struct RepeatRunner {
-    runner: OnceRunner,
-    times: i64,
+impl ProcRunner for ForRunner {
+    fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        _: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        operands.push(self.next_control()?);
+        Some(Ok(Scheduled::Proc(self.proc.clone())))
+    }
+
+    fn is_loop(&self) -> bool {
+        true
+    }
 }
 
-impl RepeatRunner {
-    pub fn new(proc: Vec<Object>, times: i64) -> Self {
+/// Runs a procedure once per element of a composite object. Each element is
+/// made of `arity` consecutive items: one for arrays and strings, a key and
+/// its value for dictionaries.
+pub struct ForallRunner {
+    proc: PsArray,
+    items: PsArray,
+    arity: usize,
+    index: usize,
+}
+
+impl ForallRunner {
+    pub fn new(proc: PsArray, items: PsArray, arity: usize) -> Self {
         Self {
-            runner: OnceRunner::new(proc),
-            times,
+            proc,
+            items,
+            arity,
+            index: 0,
         }
     }
 }
 
-impl ProcRunner for RepeatRunner {
-    fn get_object(&mut self) -> Option<Object> {
-        if self.times == 0 {
-            return None;
+impl ProcRunner for ForallRunner {
+    fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        _: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        for _ in 0..self.arity {
+            operands.push(self.items.get(self.index)?);
+            self.index += 1;
         }
+        Some(Ok(Scheduled::Proc(self.proc.clone())))
+    }
+
+    fn is_loop(&self) -> bool {
+        true
+    }
+}
+
+/// Marks the context established by `stopped`. Reached again when the
+/// procedure above it completes normally, it pushes `false`; `stop` pops it
+/// and pushes `true` instead.
+#[derive(Default)]
+pub struct StoppedRunner {
+    done: bool,
+}
+
+impl StoppedRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-        let mut object = self.runner.get_object();
-        if object.is_none() {
-            self.times -= 1;
-            if self.times == 0 {
-                return None;
+impl ProcRunner for StoppedRunner {
+    fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        _: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        if !self.done {
+            self.done = true;
+            operands.push(Object::Bool(false));
+        }
+        None
+    }
+
+    fn is_stopped_context(&self) -> bool {
+        true
+    }
+}
+
+/// Executes the PostScript program read from a file or a string, a token at
+/// a time. Procedures are built up to their closing brace and, as the values
+/// of immediately evaluated names, pushed rather than executed.
+pub struct FileRunner {
+    file: PsFile,
+    /// Whether the source is an executable string rather than a file.
+    string: bool,
+    proc_builder: ProcBuilder,
+}
+
+impl FileRunner {
+    pub fn new(file: PsFile) -> Self {
+        Self {
+            file,
+            string: false,
+            proc_builder: ProcBuilder::new(),
+        }
+    }
+
+    /// A runner executing the contents of a string.
+    pub fn string(string: &PsString) -> Self {
+        let reader = Box::new(Cursor::new(string.bytes()));
+        Self {
+            string: true,
+            ..Self::new(PsFile::reader("%string", reader))
+        }
+    }
+
+    /// The next object read, or `None` at the end of the source. The flag
+    /// tells whether the object is to be pushed on the operand stack rather
+    /// than executed: procedures and the values of immediately evaluated
+    /// names are.
+    pub fn next_object(&mut self, dict_stack: &DictStack) -> Option<Result<(Object, bool), Error>> {
+        loop {
+            let token = match self.file.next_token() {
+                Some(Ok(token)) => token,
+                Some(Err(error)) => return Some(Err(error)),
+                None if self.proc_builder.is_open() => {
+                    let command = Object::String(Literal, "{".into());
+                    return Some(Err(Error::new(SyntaxError).in_command(command)));
+                }
+                None => return None,
+            };
+            debug!("{}: {token:?}", self.file.position().unwrap());
+
+            let object = match token {
+                Token::BeginProc => {
+                    self.proc_builder.open();
+                    continue;
+                }
+                Token::EndProc if !self.proc_builder.is_open() => {
+                    let command = Object::String(Literal, "}".into());
+                    return Some(Err(Error::new(SyntaxError).in_command(command)));
+                }
+                Token::EndProc => match self.proc_builder.close() {
+                    Some(proc) => return Some(Ok((proc, true))),
+                    None => continue,
+                },
+                Token::ImmName(name) => match dict_stack.get(&Name::new(&name).into()) {
+                    Some(object) if !self.proc_builder.is_open() => {
+                        return Some(Ok((object, true)));
+                    }
+                    Some(object) => object,
+                    None => {
+                        let command = Object::Name(Literal, Name::new(&name));
+                        return Some(Err(Error::new(Undefined).in_command(command)));
+                    }
+                },
+                token => token.to_object(),
+            };
+
+            match self.proc_builder.is_open() {
+                true => self.proc_builder.push(object),
+                false => return Some(Ok((object, false))),
+            }
+        }
+    }
+}
+
+impl ProcRunner for FileRunner {
+    fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        dict_stack: &DictStack,
+    ) -> Option<Result<Scheduled, Error>> {
+        loop {
+            match self.next_object(dict_stack)? {
+                Ok((object, true)) => operands.push(object),
+                Ok((object, false)) => return Some(Ok(Scheduled::Object(object))),
+                Err(error) => return Some(Err(error)),
             }
-            self.runner.reset();
-            object = self.runner.get_object();
         }
+    }
+
+    fn file(&self) -> Option<&PsFile> {
+        match self.string {
+            true => None,
+            false => Some(&self.file),
+        }
+    }
+}
+
+/// An entry of the execution stack.
+enum Frame {
+    /// A procedure being run, at the instruction `pc` of `code`, its compiled
+    /// form as of `version`.
+    Proc {
+        proc: PsArray,
+        code: Code,
+        version: u64,
+        pc: usize,
+    },
+    Runner(Box<dyn ProcRunner>),
+}
 
-        object
+impl Frame {
+    fn runner(&self) -> Option<&dyn ProcRunner> {
+        match self {
+            Frame::Proc { .. } => None,
+            Frame::Runner(runner) => Some(runner.as_ref()),
+        }
     }
 }
 
+/// The next thing for the engine to execute.
+pub enum Step {
+    /// The instruction at an index of a compiled procedure.
+    Instruction(Code, usize),
+    /// An object, as met in a file.
+    Object(Object),
+}
+
+/// The execution stack. Procedures are run from their compiled form, which is
+/// fetched again when they are modified while they run, so that the elements
+/// still to execute are their current ones.
 #[derive(Default)]
 pub struct ExecStack {
-    pub stack: Vec<Box<dyn ProcRunner>>,
+    stack: Vec<Frame>,
 }
 
 impl ExecStack {
@@ -82,24 +398,110 @@ impl ExecStack {
         !self.stack.is_empty()
     }
 
-    pub fn get_object(&mut self) -> Option<Object> {
+    /// The next step of the topmost procedure or runner, popping the ones
+    /// which are done. A procedure is popped as its last instruction is
+    /// handed out, so that tail calls do not grow the stack.
+    pub fn next_step(
+        &mut self,
+        operands: &mut Vec<Object>,
+        dict_stack: &DictStack,
+    ) -> Option<Result<Step, Error>> {
         loop {
-            let object = match self.stack.last_mut() {
-                None => None,
-                Some(runner) => runner.get_object(),
-            };
-
-            if object.is_none() && !self.stack.is_empty() {
-                self.stack.pop();
-            } else if object.is_none() {
-                return None;
-            } else {
-                return object;
+            match self.stack.last_mut()? {
+                Frame::Proc {
+                    proc,
+                    code,
+                    version,
+                    pc,
+                } => {
+                    if proc.version() != *version {
+                        *code = proc.code();
+                        *version = proc.version();
+                    }
+                    let step = Step::Instruction(code.clone(), *pc);
+                    *pc += 1;
+                    if *pc == code.len() {
+                        self.stack.pop();
+                    }
+                    return Some(Ok(step));
+                }
+                Frame::Runner(runner) => match runner.next_step(operands, dict_stack) {
+                    None => {
+                        self.stack.pop();
+                    }
+                    Some(Ok(Scheduled::Object(object))) => return Some(Ok(Step::Object(object))),
+                    Some(Ok(Scheduled::Proc(proc))) => self.call(&proc),
+                    Some(Err(error)) => return Some(Err(error)),
+                },
             }
         }
     }
 
+    /// The innermost file being executed.
+    pub fn current_file(&self) -> Option<PsFile> {
+        self.runners().find_map(|r| r.file()).cloned()
+    }
+
+    /// Where the innermost file being executed was last read.
+    pub fn position(&self) -> Option<Position> {
+        self.runners().find_map(|r| r.file()?.position())
+    }
+
+    /// The runners, innermost first.
+    fn runners(&self) -> impl Iterator<Item = &dyn ProcRunner> {
+        self.stack.iter().rev().filter_map(Frame::runner)
+    }
+
     pub fn push(&mut self, runner: Box<dyn ProcRunner>) {
-        self.stack.push(runner);
+        self.stack.push(Frame::Runner(runner));
+    }
+
+    /// Schedule the execution of `proc`.
+    pub fn call(&mut self, proc: &PsArray) {
+        if !proc.is_empty() {
+            self.stack.push(Frame::Proc {
+                proc: proc.clone(),
+                code: proc.code(),
+                version: proc.version(),
+                pc: 0,
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Pop frames up to and including the innermost stopped context.
+    /// Returns false, leaving the stack untouched, if there is none.
+    pub fn unwind_stopped(&mut self) -> bool {
+        let position = self
+            .stack
+            .iter()
+            .rposition(|f| f.runner().is_some_and(|r| r.is_stopped_context()));
+        match position {
+            Some(index) => {
+                self.stack.truncate(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pop frames up to and including the innermost loop. Returns false,
+    /// leaving the stack untouched, if there is no loop or if a stopped
+    /// context would have to be crossed to reach it.
+    pub fn unwind_loop(&mut self) -> bool {
+        let position = self.stack.iter().rposition(|f| {
+            f.runner()
+                .is_some_and(|r| r.is_loop() || r.is_stopped_context())
+        });
+        match position {
+            Some(index) if self.stack[index].runner().is_some_and(|r| r.is_loop()) => {
+                self.stack.truncate(index);
+                true
+            }
+            _ => false,
+        }
     }
 }
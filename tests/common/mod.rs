@@ -0,0 +1,34 @@
+//! Helpers shared by the integration tests.
+
+use csgps::{Engine, Scanner};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// A writer appending to a buffer which the test keeps a handle on.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A scanner whose engine writes its standard output and error to buffers.
+pub fn scanner() -> (Scanner, SharedBuffer, SharedBuffer) {
+    let (stdout, stderr) = (SharedBuffer::default(), SharedBuffer::default());
+    let engine = Engine::with_output(Box::new(stdout.clone()), Box::new(stderr.clone()));
+    (Scanner::with_engine(engine), stdout, stderr)
+}
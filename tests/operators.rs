@@ -0,0 +1,12 @@
+mod common;
+
+use common::scanner;
+
+#[test]
+fn string_name_equality() {
+    let (mut scanner, stdout, _) = scanner();
+    scanner
+        .execute_string("(abc) /abc eq = /abc (abc) eq = (abc) /abd eq = /abc (abc) ne =")
+        .unwrap();
+    assert_eq!(stdout.contents(), "true\ntrue\nfalse\nfalse\n");
+}
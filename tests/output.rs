@@ -0,0 +1,127 @@
+mod common;
+
+use common::scanner;
+
+#[test]
+fn print_sample() {
+    let (mut scanner, stdout, stderr) = scanner();
+    scanner.execute_file("samples/print.ps").unwrap();
+
+    let expected = "\
+1
+2.5
+abc
+name
+--nostringval--
+true
+--nostringval--
+1
+2.5
+(a\\(b\\)\\\\c\\n\\001)
+/name
+name
+[1 (s) /n]
+{add 2 {3}}
+-mark-
+-dict-
+--add--
+-string-
+one two 3
+--nostringval--
+three
+two
+1
+[4]
+/three
+(two)
+1
+[-array- {a}]
+[-array- {-array-}]
+";
+    assert_eq!(stdout.contents(), expected);
+    assert_eq!(stderr.contents(), "");
+}
+
+#[test]
+fn error_report() {
+    let (mut scanner, stdout, _) = scanner();
+    assert!(scanner.execute_string("1 (a) add").is_err());
+
+    let expected = "\
+Error: /typecheck in --add-- at %string:1:7
+Operand stack:
+    1
+    (a)
+";
+    assert_eq!(stdout.contents(), expected);
+}
+
+#[test]
+fn unhandled_error() {
+    for (program, column) in [
+        ("errordict /typecheck undef 1 (a) add", 34),
+        ("errordict /handleerror {} put 1 (a) add", 37),
+    ] {
+        let (mut scanner, stdout, stderr) = scanner();
+        assert!(scanner.execute_string(program).is_err());
+
+        let expected = format!(
+            "\
+Error: /typecheck in --add-- at %string:1:{column}
+Operand stack:
+    1
+    (a)
+"
+        );
+        assert_eq!(stdout.contents(), "");
+        assert_eq!(stderr.contents(), expected);
+    }
+}
+
+#[test]
+fn stopped_contexts() {
+    let (mut scanner, stdout, stderr) = scanner();
+    scanner
+        .execute_string(
+            "{ (before) = stop (after) = } stopped =
+            { 2 { { (in) = exit } loop (out) = } repeat } stopped =
+            { 1 (a) add } stopped = == ==
+            $error /errorname get =",
+        )
+        .unwrap();
+
+    let expected = "\
+before
+true
+in
+out
+in
+out
+false
+true
+(a)
+1
+typecheck
+";
+    assert_eq!(stdout.contents(), expected);
+    assert_eq!(stderr.contents(), "");
+}
+
+#[test]
+fn errordict_handler() {
+    let (mut scanner, stdout, _) = scanner();
+    scanner
+        .execute_string("errordict /typecheck { == (caught) = } put 1 (a) add == ==")
+        .unwrap();
+    assert_eq!(stdout.contents(), "--add--\ncaught\n(a)\n1\n");
+}
+
+#[test]
+fn standard_error() {
+    let (mut scanner, stdout, stderr) = scanner();
+    scanner
+        .execute_string("(%stderr) (w) file (to stderr) writestring (to stdout) print")
+        .unwrap();
+    assert_eq!(stdout.contents(), "to stdout");
+    assert_eq!(stderr.contents(), "to stderr");
+}
@@ -0,0 +1,21 @@
+use csgps::{Token, TokenReader};
+use std::io::Cursor;
+
+/// Every token of `text`, read a line at a time.
+fn tokens(text: &str) -> Vec<Token> {
+    let mut reader = TokenReader::new("%test", Cursor::new(text.as_bytes().to_vec()));
+    std::iter::from_fn(|| reader.next_token())
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn ascii85_string_over_lines() {
+    // `>` is a base-85 digit: only `~>` ends the string
+    let text = "<~?=.)K@Uiq[An\nPdkC27X&DJsK\n6EcZ>FG'A1VH\n@($fIX_~> /x";
+    let expected: Vec<u8> = (0x5e..0x80).collect();
+    assert_eq!(
+        tokens(text),
+        [Token::String(expected), Token::LitName(b"x".to_vec())]
+    );
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::scanner;
+
+#[test]
+fn restore_leaves_other_engines_alone() {
+    let (mut a, _, _) = scanner();
+    let (mut b, b_out, _) = scanner();
+    a.execute_string("/sv save def").unwrap();
+    b.execute_string("/x (v) def").unwrap();
+    a.execute_string("sv restore").unwrap();
+    b.execute_string("x =").unwrap();
+    assert_eq!(b_out.contents(), "v\n");
+}
+
+#[test]
+fn restore_undoes_definitions() {
+    let (mut scanner, stdout, _) = scanner();
+    scanner
+        .execute_string("/x 1 def save /x 2 def x = restore x =")
+        .unwrap();
+    assert_eq!(stdout.contents(), "2\n1\n");
+}
+
+#[test]
+fn allocation_mode_is_per_engine() {
+    let (mut a, _, _) = scanner();
+    let (mut b, b_out, _) = scanner();
+    a.execute_string("true setglobal").unwrap();
+    // a local array cannot be stored in globaldict
+    b.execute_string("currentglobal = { globaldict /x 1 array put } stopped =")
+        .unwrap();
+    assert_eq!(b_out.contents(), "false\ntrue\n");
+}